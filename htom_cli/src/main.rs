@@ -4,6 +4,7 @@ use polyester::page::Page;
 use std::env;
 use std::io;
 use std::io::Read;
+use std::process;
 
 fn main() {
     let args_: Vec<String> = env::args().collect();
@@ -15,23 +16,38 @@ fn main() {
             render_html(page);
         }
 
-        ["convert"] => {
+        ["convert", ref options @ ..] => {
+            let config = parse_convert_options(options).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(1);
+            });
+
             let stdin = io::stdin();
             let mut html = String::new();
-            stdin
-                .lock()
-                .read_to_string(&mut html)
-                .expect("stdin read to end");
-
-            let markup = html_to_maud::html_to_maud(
-                &html,
-                &html_to_maud::Config {
-                    render: html_to_maud::Render::Auto,
-                    id_style: html_to_maud::IdStyle::Full,
-                    class_style: html_to_maud::ClassStyle::Full,
-                },
-            );
-            println!("{}", markup);
+
+            if let Err(err) = stdin.lock().read_to_string(&mut html) {
+                eprintln!("{}", html_to_maud::ConvertError::Io(err));
+                process::exit(1);
+            }
+
+            match html_to_maud::convert(&html, &config) {
+                Ok(conversion) => {
+                    println!("{}", conversion.maud);
+
+                    if config.render == html_to_maud::Render::Auto {
+                        eprintln!("info: rendered as {}", render_name(&conversion.render));
+                    }
+
+                    for diagnostic in conversion.diagnostics {
+                        eprintln!("warning: {}", diagnostic);
+                    }
+                }
+
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            }
         }
 
         _ => {
@@ -40,6 +56,186 @@ fn main() {
     }
 }
 
+fn parse_convert_options(options: &[&str]) -> Result<html_to_maud::Config, String> {
+    let mut config = html_to_maud::Config::default();
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        match *option {
+            "--render" => {
+                config.render = match option_value(option, options.next())? {
+                    "auto" => html_to_maud::Render::Auto,
+                    "full" => html_to_maud::Render::Full,
+                    "body" => html_to_maud::Render::OnlyBody,
+                    "head" => html_to_maud::Render::OnlyHead,
+                    "split" => html_to_maud::Render::SplitHeadBody,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--output" => {
+                config.output = match option_value(option, options.next())? {
+                    "block" => html_to_maud::Output::Block,
+                    "function" => html_to_maud::Output::Function,
+                    "render-impl" => html_to_maud::Output::RenderImpl,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--name" => {
+                let name = option_value(option, options.next())?;
+                config.item_name = Some(name.to_string());
+            }
+
+            "--layout" => {
+                config.layout = match option_value(option, options.next())? {
+                    "expanded" => html_to_maud::Layout::Expanded,
+                    "compact" => html_to_maud::Layout::Compact,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--fragment" => {
+                let context = option_value(option, options.next())?;
+                config.parse_mode = html_to_maud::ParseMode::Fragment(context.to_string());
+            }
+
+            "--id-style" => {
+                config.id_style = match option_value(option, options.next())? {
+                    "full" => html_to_maud::IdStyle::Full,
+                    "short" => html_to_maud::IdStyle::Short,
+                    "short-no-div" => html_to_maud::IdStyle::ShortNoDiv,
+                    "auto" => html_to_maud::IdStyle::Auto,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--class-style" => {
+                config.class_style = match option_value(option, options.next())? {
+                    "full" => html_to_maud::ClassStyle::Full,
+                    "short" => html_to_maud::ClassStyle::Short,
+                    "short-no-div" => html_to_maud::ClassStyle::ShortNoDiv,
+                    "auto" => html_to_maud::ClassStyle::Auto,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--attribute-order" => {
+                config.attribute_order = match option_value(option, options.next())? {
+                    "grouped" => html_to_maud::AttributeOrder::Grouped,
+                    "source" => html_to_maud::AttributeOrder::Source,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--toggles" => {
+                config.toggle_style = match option_value(option, options.next())? {
+                    "static" => html_to_maud::ToggleStyle::Static,
+                    "toggle" => html_to_maud::ToggleStyle::Toggle,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--toggle-classes" => {
+                let classes = option_value(option, options.next())?;
+                config.toggle_classes = html_to_maud::parse_name_list(classes);
+            }
+
+            "--void-elements" => {
+                let elements = option_value(option, options.next())?;
+                config.void_elements = html_to_maud::parse_name_list(elements);
+            }
+
+            "--doctype" => {
+                config.doctype_style = match option_value(option, options.next())? {
+                    "input" => html_to_maud::DoctypeStyle::Input,
+                    "always" => html_to_maud::DoctypeStyle::Always,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--comments" => {
+                config.comment_style = match option_value(option, options.next())? {
+                    "omit" => html_to_maud::CommentStyle::Omit,
+                    "line" => html_to_maud::CommentStyle::Line,
+                    "block" => html_to_maud::CommentStyle::Block,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--maud-version" => {
+                config.maud_version = match option_value(option, options.next())? {
+                    "0.23" => html_to_maud::MaudVersion::V0_23,
+                    "0.24" => html_to_maud::MaudVersion::V0_24,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--strings" => {
+                config.string_style = match option_value(option, options.next())? {
+                    "auto" => html_to_maud::StringStyle::Auto,
+                    "escaped" => html_to_maud::StringStyle::Escaped,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--unicode" => {
+                config.unicode_style = match option_value(option, options.next())? {
+                    "readable" => html_to_maud::UnicodeStyle::Readable,
+                    "escaped" => html_to_maud::UnicodeStyle::Escaped,
+                    value => return Err(invalid_value(option, value)),
+                };
+            }
+
+            "--indent" => {
+                config.indent = match option_value(option, options.next())? {
+                    "tab" => html_to_maud::Indent::Tab,
+                    value => value
+                        .parse()
+                        .map(html_to_maud::Indent::Spaces)
+                        .map_err(|_| invalid_value(option, value))?,
+                };
+            }
+
+            "--base-indent" => {
+                let value = option_value(option, options.next())?;
+                config.base_indent = value.parse().map_err(|_| invalid_value(option, value))?;
+            }
+
+            "--max-width" => {
+                let value = option_value(option, options.next())?;
+                config.max_width = value.parse().map_err(|_| invalid_value(option, value))?;
+            }
+
+            _ => {
+                return Err(format!("Unknown option: {}", option));
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+fn option_value<'a>(option: &str, value: Option<&&'a str>) -> Result<&'a str, String> {
+    value
+        .copied()
+        .ok_or_else(|| format!("Missing value for option: {}", option))
+}
+
+fn render_name(render: &html_to_maud::Render) -> &'static str {
+    match render {
+        html_to_maud::Render::Auto => "auto",
+        html_to_maud::Render::Full => "full document",
+        html_to_maud::Render::OnlyBody => "only body",
+        html_to_maud::Render::OnlyHead => "only head",
+        html_to_maud::Render::SplitHeadBody => "split head and body",
+    }
+}
+
+fn invalid_value(option: &str, value: &str) -> String {
+    format!("Invalid value for option {}: {}", option, value)
+}
+
 fn render_html<Model, Msg, AppEffect, Markup>(page: impl Page<Model, Msg, AppEffect, Markup>) {
     let (model, _effects) = page.init();
     let markup = page.view(&model);
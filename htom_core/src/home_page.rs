@@ -1,5 +1,8 @@
 use crate::html_to_maud;
-use crate::html_to_maud::{ClassStyle, IdStyle, Render};
+use crate::html_to_maud::{
+    AttributeOrder, ClassStyle, CommentStyle, DoctypeStyle, IdStyle, Indent, Layout, MaudVersion,
+    Output, ParseMode, Render, StringStyle, ToggleStyle, UnicodeStyle,
+};
 use maud::html;
 use maud::Markup;
 use polyester::browser;
@@ -26,8 +29,24 @@ enum Id {
     SettingsBackdrop,
     SettingsClose,
     RenderOptions,
+    OutputOptions,
+    ItemName,
+    ParseModeOptions,
+    DoctypeStyleOptions,
     IdStyleOptions,
     ClassStyleOptions,
+    AttributeOrderOptions,
+    ToggleStyleOptions,
+    ToggleClasses,
+    VoidElements,
+    CommentStyleOptions,
+    StringStyleOptions,
+    UnicodeStyleOptions,
+    MaudVersionOptions,
+    IndentOptions,
+    BaseIndentOptions,
+    MaxWidthOptions,
+    LayoutOptions,
     KeyboardBindings,
 }
 
@@ -42,21 +61,21 @@ impl Page<Model, Msg, AppEffect, Markup> for HomePage {
 
     fn init(&self) -> (Model, Effects<Msg, AppEffect>) {
         let html = r#"<div id="title" class="text-xl font-bold">Hello world</div>"#;
-        let maud_config = html_to_maud::Config {
-            render: Render::Auto,
-            id_style: IdStyle::Full,
-            class_style: ClassStyle::Full,
-        };
+        let maud_config = html_to_maud::Config::default();
 
-        let model = Model {
+        let mut model = Model {
             window_size: self.window_size.clone(),
             html: html.into(),
-            maud: html_to_maud::html_to_maud(html, &maud_config),
+            maud: String::new(),
+            rendered_as: Render::OnlyBody,
+            diagnostics: vec![],
             show_settings: false,
             maud_config,
             keyboard_bindings: KeyboardBindings::Default,
         };
 
+        update_maud(&mut model);
+
         let effects = vec![load_settings_effect()];
 
         (model, effects)
@@ -70,8 +89,24 @@ impl Page<Model, Msg, AppEffect, Markup> for HomePage {
                 browser::on_click(Id::SettingsBackdrop, Msg::HideSettings),
                 browser::on_click(Id::SettingsClose, Msg::HideSettings),
                 browser::on_change(Id::RenderOptions, Msg::RenderOptionChanged),
+                browser::on_change(Id::OutputOptions, Msg::OutputChanged),
+                browser::on_change_string(Id::ItemName, Msg::ItemNameChanged),
+                browser::on_change(Id::ParseModeOptions, Msg::ParseModeChanged),
+                browser::on_change(Id::DoctypeStyleOptions, Msg::DoctypeStyleChanged),
                 browser::on_change(Id::IdStyleOptions, Msg::IdStyleChanged),
                 browser::on_change(Id::ClassStyleOptions, Msg::ClassStyleChanged),
+                browser::on_change(Id::AttributeOrderOptions, Msg::AttributeOrderChanged),
+                browser::on_change(Id::ToggleStyleOptions, Msg::ToggleStyleChanged),
+                browser::on_change_string(Id::ToggleClasses, Msg::ToggleClassesChanged),
+                browser::on_change_string(Id::VoidElements, Msg::VoidElementsChanged),
+                browser::on_change(Id::CommentStyleOptions, Msg::CommentStyleChanged),
+                browser::on_change(Id::StringStyleOptions, Msg::StringStyleChanged),
+                browser::on_change(Id::UnicodeStyleOptions, Msg::UnicodeStyleChanged),
+                browser::on_change(Id::MaudVersionOptions, Msg::MaudVersionChanged),
+                browser::on_change(Id::IndentOptions, Msg::IndentChanged),
+                browser::on_change(Id::BaseIndentOptions, Msg::BaseIndentChanged),
+                browser::on_change(Id::MaxWidthOptions, Msg::MaxWidthChanged),
+                browser::on_change(Id::LayoutOptions, Msg::LayoutChanged),
                 browser::on_change(Id::KeyboardBindings, Msg::KeyboardBindingsChanged),
                 browser::on_keyup_document(browser::Key::Escape, Msg::EscapePressed),
                 window_size_sub,
@@ -95,7 +130,7 @@ impl Page<Model, Msg, AppEffect, Markup> for HomePage {
                 if let Some(settings) = maybe_settings {
                     model.keyboard_bindings = settings.keyboard_bindings;
                     model.maud_config = settings.maud_config;
-                    model.maud = html_to_maud::html_to_maud(&model.html, &model.maud_config);
+                    update_maud(model);
                 }
 
                 Ok(vec![])
@@ -112,7 +147,7 @@ impl Page<Model, Msg, AppEffect, Markup> for HomePage {
 
             Msg::HtmlChanged(html) => {
                 model.html = html.into();
-                model.maud = html_to_maud::html_to_maud(html, &model.maud_config);
+                update_maud(model);
                 Ok(vec![])
             }
 
@@ -137,19 +172,124 @@ impl Page<Model, Msg, AppEffect, Markup> for HomePage {
                     .map_err(|err| format!("Failed to parse render option: {}", err))?;
 
                 model.maud_config.render = render;
-                model.maud = html_to_maud::html_to_maud(&model.html, &model.maud_config);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::OutputChanged(value) => {
+                model.maud_config.output = value.parse().unwrap_or(Output::Block);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::ItemNameChanged(name) => {
+                model.maud_config.item_name = Some(name.trim().to_string());
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::ParseModeChanged(value) => {
+                let parse_mode = value
+                    .parse()
+                    .map_err(|err| format!("Failed to parse parse mode: {}", err))?;
+
+                model.maud_config.parse_mode = parse_mode;
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::DoctypeStyleChanged(value) => {
+                model.maud_config.doctype_style = value.parse().unwrap_or(DoctypeStyle::Input);
+                update_maud(model);
                 Ok(vec![save_settings_effect(model)])
             }
 
             Msg::IdStyleChanged(value) => {
                 model.maud_config.id_style = value.parse().unwrap_or(IdStyle::Full);
-                model.maud = html_to_maud::html_to_maud(&model.html, &model.maud_config);
+                update_maud(model);
                 Ok(vec![save_settings_effect(model)])
             }
 
             Msg::ClassStyleChanged(value) => {
                 model.maud_config.class_style = value.parse().unwrap_or(ClassStyle::Full);
-                model.maud = html_to_maud::html_to_maud(&model.html, &model.maud_config);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::AttributeOrderChanged(value) => {
+                model.maud_config.attribute_order =
+                    value.parse().unwrap_or(AttributeOrder::Grouped);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::ToggleStyleChanged(value) => {
+                model.maud_config.toggle_style = value.parse().unwrap_or(ToggleStyle::Static);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::ToggleClassesChanged(classes) => {
+                model.maud_config.toggle_classes = html_to_maud::parse_name_list(classes);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::VoidElementsChanged(elements) => {
+                model.maud_config.void_elements = html_to_maud::parse_name_list(elements);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::CommentStyleChanged(value) => {
+                model.maud_config.comment_style = value.parse().unwrap_or(CommentStyle::Omit);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::StringStyleChanged(value) => {
+                model.maud_config.string_style = value.parse().unwrap_or(StringStyle::Auto);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::UnicodeStyleChanged(value) => {
+                model.maud_config.unicode_style = value.parse().unwrap_or(UnicodeStyle::Readable);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::MaudVersionChanged(value) => {
+                let maud_version = value
+                    .parse()
+                    .map_err(|err| format!("Failed to parse maud version: {}", err))?;
+
+                model.maud_config.maud_version = maud_version;
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::IndentChanged(value) => {
+                model.maud_config.indent = value.parse().unwrap_or(Indent::Spaces(4));
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::BaseIndentChanged(value) => {
+                model.maud_config.base_indent = value.parse().unwrap_or(0);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::MaxWidthChanged(value) => {
+                model.maud_config.max_width = value.parse().unwrap_or(100);
+                update_maud(model);
+                Ok(vec![save_settings_effect(model)])
+            }
+
+            Msg::LayoutChanged(value) => {
+                model.maud_config.layout = value.parse().unwrap_or(Layout::Expanded);
+                update_maud(model);
                 Ok(vec![save_settings_effect(model)])
             }
 
@@ -264,8 +404,16 @@ fn view_editors(model: &Model, window_size: &browser::WindowSize) -> maud::Marku
                 }
             }
             div class="flex-1 pt-2 pl-4 pr-4 pb-4 lg:pt-0 lg:pl-2 lg:pr-4" {
-                div class="w-full" style=(inline_styles) {
-                    textarea #(Id::MaudOutput) class="w-full h-full text-base font-mono focus-border border border-gray-400 resize-none outline-none shadow" readonly { (model.maud) }
+                div class="w-full flex flex-col" style=(inline_styles) {
+                    textarea #(Id::MaudOutput) class="w-full flex-1 text-base font-mono focus-border border border-gray-400 resize-none outline-none shadow" readonly { (model.maud) }
+
+                    @if !model.diagnostics.is_empty() {
+                        ul class="mt-2 max-h-32 overflow-y-auto p-2 text-sm text-yellow-800 bg-yellow-50 border border-yellow-300" {
+                            @for diagnostic in &model.diagnostics {
+                                li { (diagnostic) }
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -299,21 +447,112 @@ fn view_settings_modal(model: &Model) -> maud::Markup {
                             }
 
                             (view_dropdown("Render", Id::RenderOptions, &model.maud_config.render, vec![
-                                ("Auto", &Render::Auto),
+                                (auto_render_label(&model.rendered_as), &Render::Auto),
                                 ("Full document", &Render::Full),
                                 ("Only body", &Render::OnlyBody),
+                                ("Only head", &Render::OnlyHead),
+                                ("Head and body functions", &Render::SplitHeadBody),
+                            ]))
+
+                            (view_dropdown("Output", Id::OutputOptions, &model.maud_config.output, vec![
+                                ("html! block", &Output::Block),
+                                ("Function", &Output::Function),
+                                ("Render impl", &Output::RenderImpl),
+                            ]))
+
+                            @if model.maud_config.output != Output::Block {
+                                (view_text_input("Name", Id::ItemName, model.maud_config.item_name.as_deref().unwrap_or_default(), item_name_placeholder(&model.maud_config.output)))
+                            }
+
+                            (view_dropdown("Parse as", Id::ParseModeOptions, &model.maud_config.parse_mode, vec![
+                                ("Document", &ParseMode::Document),
+                                ("Fragment", &ParseMode::Fragment("body".into())),
+                                ("Fragment in table", &ParseMode::Fragment("table".into())),
+                                ("Fragment in tbody", &ParseMode::Fragment("tbody".into())),
+                                ("Fragment in tr", &ParseMode::Fragment("tr".into())),
+                                ("Fragment in select", &ParseMode::Fragment("select".into())),
+                                ("Fragment in ul", &ParseMode::Fragment("ul".into())),
+                            ]))
+
+                            (view_dropdown("Doctype", Id::DoctypeStyleOptions, &model.maud_config.doctype_style, vec![
+                                ("From input", &DoctypeStyle::Input),
+                                ("Always", &DoctypeStyle::Always),
                             ]))
 
                             (view_dropdown("Id style", Id::IdStyleOptions, &model.maud_config.id_style, vec![
                                 ("Full", &IdStyle::Full),
                                 ("Short", &IdStyle::Short),
                                 ("Short, implicit div", &IdStyle::ShortNoDiv),
+                                ("Short when readable", &IdStyle::Auto),
                             ]))
 
                             (view_dropdown("Class style", Id::ClassStyleOptions, &model.maud_config.class_style, vec![
                                 ("Full", &ClassStyle::Full),
                                 ("Short", &ClassStyle::Short),
                                 ("Short, implicit div", &ClassStyle::ShortNoDiv),
+                                ("Short when readable", &ClassStyle::Auto),
+                            ]))
+
+                            (view_dropdown("Attribute order", Id::AttributeOrderOptions, &model.maud_config.attribute_order, vec![
+                                ("Id and classes first", &AttributeOrder::Grouped),
+                                ("Same as input", &AttributeOrder::Source),
+                            ]))
+
+                            (view_dropdown("Toggles", Id::ToggleStyleOptions, &model.maud_config.toggle_style, vec![
+                                ("Static attributes", &ToggleStyle::Static),
+                                ("Boolean attributes and classes", &ToggleStyle::Toggle),
+                            ]))
+
+                            @if model.maud_config.toggle_style == ToggleStyle::Toggle {
+                                (view_text_input("Toggle classes", Id::ToggleClasses, &model.maud_config.toggle_classes.join(" "), "active hidden"))
+                            }
+
+                            (view_text_input("Custom void elements", Id::VoidElements, &model.maud_config.void_elements.join(" "), "my-icon"))
+
+                            (view_dropdown("Comments", Id::CommentStyleOptions, &model.maud_config.comment_style, vec![
+                                ("Omit", &CommentStyle::Omit),
+                                ("Line comments", &CommentStyle::Line),
+                                ("Block comments", &CommentStyle::Block),
+                            ]))
+
+                            (view_dropdown("Strings", Id::StringStyleOptions, &model.maud_config.string_style, vec![
+                                ("Raw strings when needed", &StringStyle::Auto),
+                                ("Always escaped", &StringStyle::Escaped),
+                            ]))
+
+                            (view_dropdown("Unicode", Id::UnicodeStyleOptions, &model.maud_config.unicode_style, vec![
+                                ("Readable", &UnicodeStyle::Readable),
+                                ("Escaped", &UnicodeStyle::Escaped),
+                            ]))
+
+                            (view_dropdown("Maud version", Id::MaudVersionOptions, &model.maud_config.maud_version, vec![
+                                ("0.23", &MaudVersion::V0_23),
+                                ("0.24 or later", &MaudVersion::V0_24),
+                            ]))
+
+                            (view_dropdown("Indentation", Id::IndentOptions, &model.maud_config.indent, vec![
+                                ("2 spaces", &Indent::Spaces(2)),
+                                ("4 spaces", &Indent::Spaces(4)),
+                                ("Tabs", &Indent::Tab),
+                            ]))
+
+                            (view_dropdown("Base indentation", Id::BaseIndentOptions, &model.maud_config.base_indent, vec![
+                                ("None", &0),
+                                ("1 level", &1),
+                                ("2 levels", &2),
+                                ("3 levels", &3),
+                            ]))
+
+                            (view_dropdown("Max width", Id::MaxWidthOptions, &model.maud_config.max_width, vec![
+                                ("80", &80),
+                                ("100", &100),
+                                ("120", &120),
+                                ("160", &160),
+                            ]))
+
+                            (view_dropdown("Layout", Id::LayoutOptions, &model.maud_config.layout, vec![
+                                ("Expanded", &Layout::Expanded),
+                                ("Compact", &Layout::Compact),
                             ]))
 
                             (view_dropdown("Keyboard bindings", Id::KeyboardBindings, &model.keyboard_bindings, vec![
@@ -335,6 +574,31 @@ fn view_settings_modal(model: &Model) -> maud::Markup {
     }
 }
 
+fn auto_render_label(rendered_as: &Render) -> &'static str {
+    match rendered_as {
+        Render::Full => "Auto (full document)",
+        _ => "Auto (only body)",
+    }
+}
+
+fn item_name_placeholder(output: &Output) -> &'static str {
+    match output {
+        Output::RenderImpl => "View",
+        _ => "view",
+    }
+}
+
+fn view_text_input(title: &str, id: Id, value: &str, placeholder: &str) -> maud::Markup {
+    html! {
+        div class="mt-4" {
+            label class="block text-sm font-medium text-gray-700" for=(id) {
+                (title)
+            }
+            input #(id) type="text" class="mt-1 block w-full pl-3 pr-3 py-2 text-base border-gray-300 focus:outline-none focus:ring-indigo-500 focus:border-indigo-500 sm:text-sm rounded-md" value=(value) placeholder=(placeholder);
+        }
+    }
+}
+
 fn view_dropdown<V>(title: &str, id: Id, selected_value: V, options: Vec<(&str, V)>) -> maud::Markup
 where
     V: PartialEq,
@@ -362,6 +626,8 @@ pub struct Model {
     pub window_size: Option<browser::WindowSize>,
     pub html: String,
     pub maud: String,
+    pub rendered_as: Render,
+    pub diagnostics: Vec<String>,
     pub show_settings: bool,
     pub maud_config: html_to_maud::Config,
     pub keyboard_bindings: KeyboardBindings,
@@ -375,8 +641,24 @@ pub enum Msg {
     HideSettings,
     EscapePressed,
     RenderOptionChanged(browser::Value),
+    OutputChanged(browser::Value),
+    ItemNameChanged(String),
+    ParseModeChanged(browser::Value),
+    DoctypeStyleChanged(browser::Value),
     IdStyleChanged(browser::Value),
     ClassStyleChanged(browser::Value),
+    AttributeOrderChanged(browser::Value),
+    ToggleStyleChanged(browser::Value),
+    ToggleClassesChanged(String),
+    VoidElementsChanged(String),
+    CommentStyleChanged(browser::Value),
+    StringStyleChanged(browser::Value),
+    UnicodeStyleChanged(browser::Value),
+    MaudVersionChanged(browser::Value),
+    IndentChanged(browser::Value),
+    BaseIndentChanged(browser::Value),
+    MaxWidthChanged(browser::Value),
+    LayoutChanged(browser::Value),
     KeyboardBindingsChanged(browser::Value),
     GotSettings(browser::Value),
 }
@@ -413,6 +695,26 @@ pub struct LocalStorageSettings {
     pub keyboard_bindings: KeyboardBindings,
 }
 
+// A failed conversion clears the output, so the previous input's maud is not left on screen
+fn update_maud(model: &mut Model) {
+    match html_to_maud::convert(&model.html, &model.maud_config) {
+        Ok(conversion) => {
+            model.maud = conversion.maud;
+            model.rendered_as = conversion.render;
+            model.diagnostics = conversion
+                .diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect();
+        }
+
+        Err(err) => {
+            model.maud = String::new();
+            model.diagnostics = vec![format!("Failed to convert html: {}", err)];
+        }
+    }
+}
+
 fn load_settings_effect() -> Effect<Msg, AppEffect> {
     local_storage::get_item("settings", Msg::GotSettings)
 }
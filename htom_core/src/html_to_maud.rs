@@ -1,15 +1,29 @@
-use html5ever::parse_document;
-use html5ever::tendril::TendrilSink;
+use crate::pretty;
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::{
+    BufferQueue, Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+    TokenizerResult,
+};
+use html5ever::tree_builder::{create_element, TreeBuilder, TreeSink};
+use html5ever::{namespace_url, ns};
+use html5ever::{LocalName, QualName};
 use markup5ever_rcdom::{Handle, NodeData, RcDom};
 use serde::{Deserialize, Serialize};
 use std::default::Default;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+const MAX_DEPTH: usize = 512;
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(rename_all = "camelCase")]
 pub enum Render {
     Auto,
     Full,
     OnlyBody,
+    OnlyHead,
+    SplitHeadBody,
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -18,6 +32,8 @@ pub enum IdStyle {
     Full,
     Short,
     ShortNoDiv,
+    // Shorthand when the id can be written without escapes
+    Auto,
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -26,244 +42,1672 @@ pub enum ClassStyle {
     Full,
     Short,
     ShortNoDiv,
+    // Shorthand when all classes can be written without escapes
+    Auto,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Output {
+    // A bare html! block
+    Block,
+    // A function returning Markup
+    Function,
+    // A struct implementing maud::Render
+    RenderImpl,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Indent {
+    Spaces(usize),
+    Tab,
+}
+
+impl Indent {
+    fn repeat(&self, levels: usize) -> String {
+        match self {
+            Indent::Spaces(width) => " ".repeat(width * levels),
+            Indent::Tab => "\t".repeat(levels),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ParseMode {
+    Document,
+    // Parse as the children of the given context element, i.e. "table" or "select"
+    Fragment(String),
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DoctypeStyle {
+    // Render the doctype of the input, if any
+    Input,
+    // Render the html5 doctype when the input does not have one
+    Always,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AttributeOrder {
+    // Id first, then classes, then the other attributes in source order
+    Grouped,
+    Source,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ToggleStyle {
+    Static,
+    // Boolean attributes and the toggle classes are written as toggles, i.e. checked[true], to be
+    // replaced with real conditions
+    Toggle,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Layout {
+    Expanded,
+    // Empty elements and elements with only text and inline elements are kept on one line
+    Compact,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CommentStyle {
+    Omit,
+    Line,
+    Block,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StringStyle {
+    // Raw strings for values containing quotes or backslashes, escaped strings otherwise
+    Auto,
+    Escaped,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum UnicodeStyle {
+    // Printable characters are kept as is, invisible characters are escaped
+    Readable,
+    Escaped,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum MaudVersion {
+    #[serde(rename = "0.23")]
+    V0_23,
+    // Adds support for quoted attribute names, i.e. "@click"="open = true"
+    #[serde(rename = "0.24")]
+    V0_24,
+}
+
+impl fmt::Display for MaudVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MaudVersion::V0_23 => write!(f, "0.23"),
+            MaudVersion::V0_24 => write!(f, "0.24"),
+        }
+    }
+}
+
+impl MaudVersion {
+    fn supports_quoted_attribute_names(&self) -> bool {
+        *self != MaudVersion::V0_23
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub render: Render,
+    pub output: Output,
+    // Name of the function or struct, defaults to view or View
+    pub item_name: Option<String>,
+    pub indent: Indent,
+    // Number of indentation levels added to every line, for pasting into nested code
+    pub base_indent: usize,
+    // Elements are wrapped to fit in this width where possible
+    pub max_width: usize,
+    pub layout: Layout,
     pub id_style: IdStyle,
     pub class_style: ClassStyle,
+    pub attribute_order: AttributeOrder,
+    pub toggle_style: ToggleStyle,
+    pub toggle_classes: Vec<String>,
+    // Custom elements that are written without a closing tag, in addition to the html ones
+    pub void_elements: Vec<String>,
+    pub parse_mode: ParseMode,
+    pub doctype_style: DoctypeStyle,
+    pub comment_style: CommentStyle,
+    pub maud_version: MaudVersion,
+    pub string_style: StringStyle,
+    pub unicode_style: UnicodeStyle,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            render: Render::Auto,
+            output: Output::Block,
+            item_name: None,
+            indent: Indent::Spaces(4),
+            base_indent: 0,
+            max_width: 100,
+            layout: Layout::Expanded,
+            id_style: IdStyle::Full,
+            class_style: ClassStyle::Full,
+            attribute_order: AttributeOrder::Grouped,
+            toggle_style: ToggleStyle::Static,
+            toggle_classes: vec!["active".to_string(), "hidden".to_string()],
+            void_elements: vec![],
+            parse_mode: ParseMode::Document,
+            doctype_style: DoctypeStyle::Input,
+            comment_style: CommentStyle::Omit,
+            maud_version: MaudVersion::V0_24,
+            string_style: StringStyle::Auto,
+            unicode_style: UnicodeStyle::Readable,
+        }
+    }
+}
+
+// Parses the toggle classes and void elements options, the names are separated by commas or
+// whitespace
+pub fn parse_name_list(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+pub struct Conversion {
+    pub maud: String,
+    // The render mode that was used, Auto is resolved to either Full or OnlyBody
+    pub render: Render,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    UnsupportedAttributeName {
+        tag_name: String,
+        name: String,
+        maud_version: MaudVersion,
+    },
+    InvalidItemName {
+        name: String,
+        fallback: String,
+    },
+    // Repeated classes are merged, for any other attribute only the first value is kept
+    DuplicateAttribute {
+        tag_name: String,
+        name: String,
+        value: String,
+    },
+    ConflictingIds {
+        tag_name: String,
+        kept: String,
+        dropped: Vec<String>,
+    },
+    // Browsers ignore a repeated class attribute, but its classes were most likely meant to apply
+    MergedClassAttributes {
+        tag_name: String,
+        classes: Vec<String>,
+    },
+    DuplicateClasses {
+        tag_name: String,
+        classes: Vec<String>,
+    },
+    VoidElementWithChildren {
+        tag_name: String,
+    },
+    // Splitting head and body always writes the view_head and view_body functions
+    SplitRenderImpl,
+    UnusedItemName {
+        name: String,
+    },
+    // The render mode only includes one of the head and body, but the other has content too
+    OmittedContent {
+        tag_name: String,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::UnsupportedAttributeName {
+                tag_name,
+                name,
+                maud_version,
+            } => write!(
+                f,
+                "Attribute '{}' on <{}> needs a quoted name, which requires maud 0.24 or later (targeting maud {})",
+                name, tag_name, maud_version
+            ),
+
+            Diagnostic::InvalidItemName { name, fallback } => write!(
+                f,
+                "'{}' is not a valid rust identifier, using '{}' instead",
+                name, fallback
+            ),
+
+            Diagnostic::DuplicateAttribute {
+                tag_name,
+                name,
+                value,
+            } => write!(
+                f,
+                "<{}> has a repeated {} attribute, dropping '{}' and keeping the first value",
+                tag_name, name, value
+            ),
+
+            Diagnostic::ConflictingIds {
+                tag_name,
+                kept,
+                dropped,
+            } => write!(
+                f,
+                "<{}> has multiple ids, keeping '{}' and dropping '{}'",
+                tag_name,
+                kept,
+                dropped.join("', '")
+            ),
+
+            Diagnostic::MergedClassAttributes { tag_name, classes } => write!(
+                f,
+                "<{}> has a repeated class attribute, which browsers ignore, merging '{}' into the first one",
+                tag_name,
+                classes.join("', '")
+            ),
+
+            Diagnostic::DuplicateClasses { tag_name, classes } => write!(
+                f,
+                "Removed duplicate classes on <{}>: {}",
+                tag_name,
+                classes.join(", ")
+            ),
+
+            Diagnostic::VoidElementWithChildren { tag_name } => write!(
+                f,
+                "<{}> is a void element but has children, it is written as a normal element to keep them",
+                tag_name
+            ),
+
+            Diagnostic::SplitRenderImpl => write!(
+                f,
+                "A Render impl can not be split into head and body, writing functions instead"
+            ),
+
+            Diagnostic::UnusedItemName { name } => write!(
+                f,
+                "The name '{}' is not used when splitting head and body, the functions are named view_head and view_body",
+                name
+            ),
+
+            Diagnostic::OmittedContent { tag_name } => write!(
+                f,
+                "The content of <{}> is not rendered, render the full document or split head and body to keep it",
+                tag_name
+            ),
+        }
+    }
+}
+
+enum Doctype {
+    Html5,
+    // A PreEscaped splice of any other doctype
+    Other(String),
 }
 
 struct Doc {
-    input: String,
-    head: Vec<String>,
-    body: Vec<String>,
+    doctype: Option<Doctype>,
+    html_element: Option<pretty::Doc>,
+    head_element: pretty::Doc,
+    body_element: pretty::Doc,
+    head: Vec<pretty::Doc>,
+    body: Vec<pretty::Doc>,
+    diagnostics: Vec<Diagnostic>,
+    // Comments outside of the head and body go in the head until it is closed, if it is rendered
+    renders_head: bool,
+    is_head_closed: bool,
 }
 
 impl Doc {
-    fn new(input: &str) -> Doc {
+    fn new(render: &Render) -> Doc {
         Doc {
-            input: input.to_string(),
+            doctype: None,
+            html_element: None,
+            head_element: pretty::text("head {"),
+            body_element: pretty::text("body {"),
             head: vec![],
             body: vec![],
+            diagnostics: vec![],
+            renders_head: matches!(
+                render,
+                Render::Full | Render::OnlyHead | Render::SplitHeadBody
+            ),
+            is_head_closed: false,
+        }
+    }
+
+    fn render(&self, render: &Render, output: &Output, item_name: &str) -> pretty::Doc {
+        let imports = pretty::text(self.render_imports(render, output));
+
+        let items = match (render, output) {
+            (Render::SplitHeadBody, Output::Block) => vec![self.render_split_head_body()],
+
+            (Render::SplitHeadBody, _) => vec![imports, self.render_split_head_body()],
+
+            (_, Output::Block) => vec![self.render_markup(render, output)],
+
+            (_, Output::Function) => {
+                let function = self.block(
+                    &format!("pub fn {}() -> Markup", item_name),
+                    vec![self.render_markup(render, output)],
+                );
+
+                vec![imports, function]
+            }
+
+            (_, Output::RenderImpl) => {
+                let render_fn = self.block(
+                    "fn render(&self) -> Markup",
+                    vec![self.render_markup(render, output)],
+                );
+                let render_impl =
+                    self.block(&format!("impl Render for {}", item_name), vec![render_fn]);
+                let render_struct = pretty::text(format!("pub struct {};", item_name));
+
+                vec![imports, render_struct, render_impl]
+            }
+        };
+
+        blank_line_separated(items)
+    }
+
+    fn render_imports(&self, render: &Render, output: &Output) -> String {
+        let uses_doctype = *render == Render::Full && matches!(self.doctype, Some(Doctype::Html5));
+
+        let imports = [
+            Some("html"),
+            Some("Markup"),
+            Some("Render")
+                .filter(|_| *output == Output::RenderImpl && *render != Render::SplitHeadBody),
+            Some("DOCTYPE").filter(|_| uses_doctype),
+        ];
+
+        let imports = imports
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("use maud::{{{}}};", imports)
+    }
+
+    fn render_markup(&self, render: &Render, output: &Output) -> pretty::Doc {
+        match render {
+            Render::Full => self.render_full(output),
+            Render::OnlyHead => self.block("html!", self.head.clone()),
+            _ => self.block("html!", self.body.clone()),
+        }
+    }
+
+    fn render_full(&self, output: &Output) -> pretty::Doc {
+        let document = vec![
+            block(self.head_element.clone(), self.head.clone()),
+            block(self.body_element.clone(), self.body.clone()),
+        ];
+
+        // The html element is optional and only rendered when it has attributes
+        let document = match &self.html_element {
+            Some(html_element) => vec![block(html_element.clone(), document)],
+            None => document,
+        };
+
+        // DOCTYPE is imported when rendering a complete item
+        let doctype = self
+            .doctype
+            .as_ref()
+            .map(|doctype| match (doctype, output) {
+                (Doctype::Html5, Output::Block) => pretty::text("(maud::DOCTYPE)"),
+                (Doctype::Html5, _) => pretty::text("(DOCTYPE)"),
+                (Doctype::Other(doctype), _) => pretty::text(doctype),
+            });
+
+        let content = doctype.into_iter().chain(document).collect();
+        self.block("html!", content)
+    }
+
+    // Renders functions matching the head and body of a polyester PageMarkup
+    fn render_split_head_body(&self) -> pretty::Doc {
+        let view_head = self.block(
+            "fn view_head() -> Markup",
+            vec![self.block("html!", self.head.clone())],
+        );
+        let view_body = self.block(
+            "fn view_body() -> Markup",
+            vec![self.block("html!", self.body.clone())],
+        );
+
+        blank_line_separated(vec![view_head, view_body])
+    }
+
+    fn block(&self, head: &str, content: Vec<pretty::Doc>) -> pretty::Doc {
+        block(pretty::text(format!("{} {{", head)), content)
+    }
+
+    fn set_root_element(&mut self, config: &Config, elem: &Element) {
+        match elem.tag_name.as_str() {
+            "html" if elem.has_attributes() => self.html_element = Some(elem.to_maud(config)),
+            "head" => {
+                self.head_element = elem.to_maud(config);
+                self.is_head_closed = true;
+            }
+            "body" => self.body_element = elem.to_maud(config),
+            _ => (),
+        }
+    }
+
+    fn extend(&mut self, parent: &Parent, content: Vec<pretty::Doc>) {
+        match parent {
+            Parent::Head => self.head.extend(content),
+            Parent::Body => self.body.extend(content),
+            Parent::Other if self.renders_head && !self.is_head_closed => self.head.extend(content),
+            Parent::Other => self.body.extend(content),
+        }
+    }
+}
+
+// Puts the content on separate lines between the opening line and a closing brace
+fn block(open: pretty::Doc, content: Vec<pretty::Doc>) -> pretty::Doc {
+    let content = content
+        .into_iter()
+        .flat_map(|doc| [pretty::hardline(), doc])
+        .collect();
+
+    pretty::concat(vec![
+        open,
+        pretty::nest(pretty::concat(content)),
+        pretty::hardline(),
+        pretty::text("}"),
+    ])
+}
+
+fn element_block(
+    config: &Config,
+    open: pretty::Doc,
+    content: Vec<pretty::Doc>,
+    is_inline: bool,
+) -> pretty::Doc {
+    match config.layout {
+        Layout::Compact if content.is_empty() => pretty::concat(vec![open, pretty::text("}")]),
+
+        // The group is printed on one line when it fits, a block child or a multiline string
+        // never fits so such elements are always broken
+        Layout::Compact if is_inline => {
+            let content = content
+                .into_iter()
+                .flat_map(|doc| [pretty::line(), doc])
+                .collect();
+
+            pretty::group(pretty::concat(vec![
+                open,
+                pretty::nest(pretty::concat(content)),
+                pretty::line(),
+                pretty::text("}"),
+            ]))
+        }
+
+        _ => block(open, content),
+    }
+}
+
+fn blank_line_separated(docs: Vec<pretty::Doc>) -> pretty::Doc {
+    pretty::join(
+        docs,
+        pretty::concat(vec![pretty::hardline(), pretty::hardline()]),
+    )
+}
+
+#[derive(Debug)]
+pub enum ConvertError {
+    Io(io::Error),
+    UnsupportedNode(String),
+    LimitExceeded { limit: String, max: usize },
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConvertError::Io(err) => write!(f, "Failed to read html: {}", err),
+            ConvertError::UnsupportedNode(node) => write!(f, "Unsupported node: {}", node),
+            ConvertError::LimitExceeded { limit, max } => {
+                write!(f, "Limit exceeded: {} (max {})", limit, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl From<io::Error> for ConvertError {
+    fn from(err: io::Error) -> Self {
+        ConvertError::Io(err)
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Parent {
+    Head,
+    Body,
+    Other,
+}
+
+#[deprecated(
+    note = "errors are returned as the output, use `try_html_to_maud` or `convert` instead"
+)]
+pub fn html_to_maud(html: &str, config: &Config) -> String {
+    try_html_to_maud(html, config).unwrap_or_else(|err| err.to_string())
+}
+
+pub fn try_html_to_maud(html: &str, config: &Config) -> Result<String, ConvertError> {
+    convert(html, config).map(|conversion| conversion.maud)
+}
+
+pub fn convert(html: &str, config: &Config) -> Result<Conversion, ConvertError> {
+    // Tag names are case insensitive, the context is lowercased once so that the parser and the
+    // whitespace rules agree on it
+    let config = &Config {
+        parse_mode: match &config.parse_mode {
+            ParseMode::Fragment(context) => ParseMode::Fragment(context.to_ascii_lowercase()),
+            ParseMode::Document => ParseMode::Document,
+        },
+        ..config.clone()
+    };
+
+    let parsed = parse(html, &config.parse_mode);
+    let dom = parsed.dom;
+    let render = resolve_render(config, parsed.is_document);
+
+    let mut doc = Doc::new(&render);
+    doc.diagnostics = parsed.diagnostics;
+
+    match &config.parse_mode {
+        ParseMode::Document => {
+            walk(config, 0, &dom.document, &mut doc, &Parent::Other)?;
+        }
+
+        ParseMode::Fragment(_) => {
+            // The fragment nodes are children of a synthetic html root element
+            for root in dom.document.children.borrow().iter() {
+                for child in root.children.borrow().iter() {
+                    let content = walk(config, 1, child, &mut doc, &Parent::Body)?;
+                    doc.extend(&Parent::Body, content);
+                }
+            }
+        }
+    }
+
+    if doc.doctype.is_none() && config.doctype_style == DoctypeStyle::Always {
+        doc.doctype = Some(Doctype::Html5);
+    }
+
+    let omitted = match render {
+        Render::OnlyBody if !doc.head.is_empty() => Some("head"),
+        Render::OnlyHead if !doc.body.is_empty() => Some("body"),
+        _ => None,
+    };
+
+    doc.diagnostics
+        .extend(omitted.map(|tag_name| Diagnostic::OmittedContent {
+            tag_name: tag_name.to_string(),
+        }));
+
+    let item_name = match render {
+        Render::SplitHeadBody => {
+            doc.diagnostics.extend(split_output_diagnostics(config));
+            String::new()
+        }
+
+        _ => resolve_item_name(config, &mut doc.diagnostics),
+    };
+
+    Ok(Conversion {
+        maud: pretty::render(
+            &doc.render(&render, &config.output, &item_name),
+            config.max_width,
+            &config.indent.repeat(1),
+            config.base_indent,
+        ),
+        render,
+        diagnostics: doc.diagnostics,
+    })
+}
+
+fn resolve_item_name(config: &Config, diagnostics: &mut Vec<Diagnostic>) -> String {
+    let fallback = match config.output {
+        Output::RenderImpl => "View",
+        _ => "view",
+    };
+
+    match config.item_name.as_deref().filter(|name| !name.is_empty()) {
+        Some(name) if is_rust_identifier(name) && !RUST_KEYWORDS.contains(&name) => {
+            name.to_string()
+        }
+
+        Some(name) if config.output != Output::Block => {
+            diagnostics.push(Diagnostic::InvalidItemName {
+                name: name.to_string(),
+                fallback: fallback.to_string(),
+            });
+
+            fallback.to_string()
+        }
+
+        _ => fallback.to_string(),
+    }
+}
+
+fn split_output_diagnostics(config: &Config) -> Vec<Diagnostic> {
+    let render_impl =
+        Some(Diagnostic::SplitRenderImpl).filter(|_| config.output == Output::RenderImpl);
+
+    let unused_name = config
+        .item_name
+        .as_ref()
+        .filter(|name| !name.is_empty() && config.output != Output::Block)
+        .map(|name| Diagnostic::UnusedItemName { name: name.clone() });
+
+    render_impl.into_iter().chain(unused_name).collect()
+}
+
+fn resolve_render(config: &Config, is_document: bool) -> Render {
+    match config.render {
+        Render::Auto => {
+            let is_fragment = config.parse_mode != ParseMode::Document;
+
+            if !is_fragment && is_document {
+                Render::Full
+            } else {
+                Render::OnlyBody
+            }
+        }
+
+        ref render => render.clone(),
+    }
+}
+
+struct Parsed {
+    dom: RcDom,
+    is_document: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn parse(html: &str, parse_mode: &ParseMode) -> Parsed {
+    let (tree_builder, initial_state) = match parse_mode {
+        ParseMode::Document => (TreeBuilder::new(RcDom::default(), Default::default()), None),
+
+        ParseMode::Fragment(context) => {
+            let mut dom = RcDom::default();
+            let context_name = QualName::new(None, ns!(html), LocalName::from(context.as_str()));
+            let context_element = create_element(&mut dom, context_name, vec![]);
+            let tree_builder =
+                TreeBuilder::new_for_fragment(dom, context_element, None, Default::default());
+            let initial_state = tree_builder.tokenizer_state_for_context_elem();
+
+            (tree_builder, Some(initial_state))
+        }
+    };
+
+    let scanner = TokenScanner {
+        tree_builder,
+        html,
+        is_document: false,
+        offset: 0,
+        token_end: 0,
+        has_duplicate_attribute: false,
+        diagnostics: vec![],
+    };
+
+    let opts = TokenizerOpts {
+        initial_state,
+        ..Default::default()
+    };
+
+    let mut tokenizer = Tokenizer::new(scanner, opts);
+    let mut input = BufferQueue::new();
+
+    // The input is fed one character at a time, so the scanner knows where each token ends
+    for (offset, c) in html.char_indices() {
+        tokenizer.sink.offset = offset + c.len_utf8();
+        input.push_back(StrTendril::from_char(c));
+        while let TokenizerResult::Script(_) = tokenizer.feed(&mut input) {}
+    }
+
+    tokenizer.end();
+
+    let scanner = tokenizer.sink;
+
+    Parsed {
+        dom: scanner.tree_builder.sink.finish(),
+        is_document: scanner.is_document,
+        diagnostics: scanner.diagnostics,
+    }
+}
+
+// Some information is lost when the tree is built, so the tokens are inspected on their way to
+// the tree builder. The tree builder always creates html, head and body elements, which makes it
+// impossible to tell if the input is a complete document, and the tokenizer drops all but the
+// first of repeated attributes. Going through the tree builder keeps the tokenizer in the same
+// state as the parser, i.e. markup in scripts or in a textarea context is not mistaken for tags.
+struct TokenScanner<'a> {
+    tree_builder: TreeBuilder<Handle, RcDom>,
+    html: &'a str,
+    is_document: bool,
+    // Byte offsets of the input fed so far and of the end of the last token
+    offset: usize,
+    token_end: usize,
+    // The duplicate attribute error is emitted before the tag it belongs to
+    has_duplicate_attribute: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> TokenSink for TokenScanner<'a> {
+    type Handle = Handle;
+
+    fn process_token(&mut self, token: Token, line_number: u64) -> TokenSinkResult<Handle> {
+        let token = self.inspect(token);
+        self.tree_builder.process_token(token, line_number)
+    }
+
+    fn end(&mut self) {
+        self.tree_builder.end();
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&self) -> bool {
+        self.tree_builder
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+}
+
+impl<'a> TokenScanner<'a> {
+    fn inspect(&mut self, mut token: Token) -> Token {
+        // Errors are emitted in the middle of a tag, so they don't end a token
+        if let Token::ParseError(error) = &token {
+            if error == "Duplicate attribute" {
+                self.has_duplicate_attribute = true;
+            }
+
+            return token;
+        }
+
+        let token_start = std::mem::replace(&mut self.token_end, self.offset);
+
+        match &mut token {
+            Token::DoctypeToken(_) => {
+                self.is_document = true;
+            }
+
+            Token::TagToken(tag) if tag.kind == TagKind::StartTag => {
+                if matches!(&*tag.name, "html" | "head" | "body") {
+                    self.is_document = true;
+                }
+
+                if std::mem::take(&mut self.has_duplicate_attribute) {
+                    self.recover_duplicate_attributes(tag, token_start);
+                }
+            }
+
+            Token::TagToken(_) => {
+                self.has_duplicate_attribute = false;
+            }
+
+            _ => (),
         }
+
+        token
     }
 
-    fn render(&self, config: &Config) -> String {
-        match config.render {
-            Render::Auto => {
-                let root_elems = vec!["<html>", "<head>", "<body>"];
-                let render_only_body = !root_elems.iter().any(|elem| self.input.contains(elem));
+    // Reads the repeated attributes from the source of the tag, which starts at the first < after
+    // the previous token. Classes are merged, and any other value that is dropped is reported.
+    fn recover_duplicate_attributes(&mut self, tag: &mut Tag, token_start: usize) {
+        let source = &self.html[token_start..self.offset];
+        let source_tag = match source.find('<') {
+            Some(index) => lex_start_tag(&source[index..]),
+            None => return,
+        };
+
+        if !source_tag.name.eq_ignore_ascii_case(&tag.name) {
+            return;
+        }
+
+        let tag_name = tag.name.to_string();
+        let values = decode_attribute_values(&source_tag.attributes);
+
+        let mut seen: Vec<&str> = vec![];
+        let mut merged_classes = vec![];
+        let mut dropped_ids = vec![];
+
+        for ((name, _), value) in source_tag.attributes.iter().zip(values) {
+            if !seen.contains(&name.as_str()) {
+                seen.push(name);
+                continue;
+            }
+
+            let kept = match tag.attrs.iter_mut().find(|attr| &*attr.name.local == name) {
+                Some(kept) => kept,
+                None => continue,
+            };
+
+            if *kept.value == value {
+                continue;
+            }
+
+            match name.as_str() {
+                "class" => {
+                    kept.value.push_char(' ');
+                    kept.value.push_slice(&value);
+                    merged_classes.push(value);
+                }
+
+                "id" => dropped_ids.push(value),
+
+                _ => self.diagnostics.push(Diagnostic::DuplicateAttribute {
+                    tag_name: tag_name.clone(),
+                    name: name.clone(),
+                    value,
+                }),
+            }
+        }
+
+        if !merged_classes.is_empty() {
+            self.diagnostics.push(Diagnostic::MergedClassAttributes {
+                tag_name: tag_name.clone(),
+                classes: merged_classes,
+            });
+        }
+
+        if !dropped_ids.is_empty() {
+            let kept = tag
+                .attrs
+                .iter()
+                .find(|attr| &*attr.name.local == "id")
+                .map(|attr| attr.value.to_string())
+                .unwrap_or_default();
+
+            self.diagnostics.push(Diagnostic::ConflictingIds {
+                tag_name,
+                kept,
+                dropped: dropped_ids,
+            });
+        }
+    }
+}
+
+struct SourceTag {
+    name: String,
+    // Lowercase names with the raw values, including quotes
+    attributes: Vec<(String, Option<String>)>,
+}
+
+// Splits the source of a start tag the way the tokenizer does, but keeps every attribute
+fn lex_start_tag(source: &str) -> SourceTag {
+    let mut chars = source.chars().skip(1).peekable();
+    let is_name_end = |c: &char| is_html_whitespace(*c) || matches!(c, '/' | '>');
+    let skip_whitespace = |chars: &mut std::iter::Peekable<_>| {
+        while chars.next_if(|c| is_html_whitespace(*c)).is_some() {}
+    };
+
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| !is_name_end(c)) {
+        name.push(c);
+    }
+
+    let mut attributes = vec![];
+
+    while let Some(c) = chars.next() {
+        match c {
+            '>' => break,
+            '/' => (),
+            _ if is_html_whitespace(c) => (),
+
+            // The first character may be =, later ones end the name
+            _ => {
+                let mut attribute_name = c.to_string();
+                while let Some(c) = chars.next_if(|c| !is_name_end(c) && *c != '=') {
+                    attribute_name.push(c);
+                }
+
+                skip_whitespace(&mut chars);
+
+                let value = chars.next_if_eq(&'=').map(|_| {
+                    skip_whitespace(&mut chars);
+
+                    match chars.next_if(|c| matches!(c, '"' | '\'')) {
+                        Some(quote) => {
+                            let mut value = quote.to_string();
+                            for c in chars.by_ref() {
+                                value.push(c);
+
+                                if c == quote {
+                                    break;
+                                }
+                            }
+                            value
+                        }
+
+                        None => {
+                            let mut value = String::new();
+                            while let Some(c) =
+                                chars.next_if(|c| !is_html_whitespace(*c) && *c != '>')
+                            {
+                                value.push(c);
+                            }
+                            value
+                        }
+                    }
+                });
+
+                attributes.push((attribute_name.to_ascii_lowercase(), value));
+            }
+        }
+    }
+
+    SourceTag { name, attributes }
+}
+
+// Character references are decoded by the tokenizer, with each raw value under a unique name
+fn decode_attribute_values(attributes: &[(String, Option<String>)]) -> Vec<String> {
+    let source = attributes
+        .iter()
+        .enumerate()
+        .map(|(index, (_, value))| match value {
+            Some(value) if !value.is_empty() => format!(" a{}={}", index, value),
+            _ => format!(" a{}", index),
+        })
+        .collect::<String>();
+
+    let mut input = BufferQueue::new();
+    input.push_back(StrTendril::from(format!("<x{}>", source)));
+
+    let mut tokenizer = Tokenizer::new(AttributeCollector::default(), Default::default());
+    let _ = tokenizer.feed(&mut input);
+    tokenizer.end();
+
+    tokenizer
+        .sink
+        .attributes
+        .into_iter()
+        .map(|attr| attr.value.to_string())
+        .collect()
+}
+
+#[derive(Default)]
+struct AttributeCollector {
+    attributes: Vec<html5ever::Attribute>,
+}
+
+impl TokenSink for AttributeCollector {
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        if let Token::TagToken(tag) = token {
+            self.attributes.extend(tag.attrs);
+        }
+
+        TokenSinkResult::Continue
+    }
+}
+
+// Returns the maud for the node, the content of the root elements is added to the doc instead
+fn walk(
+    config: &Config,
+    depth: usize,
+    node: &Handle,
+    doc: &mut Doc,
+    parent: &Parent,
+) -> Result<Vec<pretty::Doc>, ConvertError> {
+    if depth > MAX_DEPTH {
+        return Err(ConvertError::LimitExceeded {
+            limit: "element nesting depth".to_string(),
+            max: MAX_DEPTH,
+        });
+    }
+
+    match &node.data {
+        NodeData::Document => {
+            for child in node.children.borrow().iter() {
+                let content = walk(config, depth + 1, child, doc, parent)?;
+                doc.extend(parent, content);
+            }
+
+            Ok(vec![])
+        }
+
+        NodeData::Doctype {
+            name,
+            public_id,
+            system_id,
+        } => {
+            doc.doctype = Some(format_doctype(config, name, public_id, system_id));
+            Ok(vec![])
+        }
+
+        NodeData::Text { contents } => {
+            let text = normalize_text(config, node, &contents.borrow());
+
+            Ok(text
+                .map(|text| pretty::text(string_literal(&text, config)))
+                .into_iter()
+                .collect())
+        }
+
+        NodeData::Comment { contents } => Ok(format_comment(&config.comment_style, contents)
+            .into_iter()
+            .map(pretty::text)
+            .collect()),
+
+        NodeData::Element {
+            name,
+            attrs,
+            template_contents,
+            ..
+        } => {
+            let attributes = attrs
+                .borrow()
+                .iter()
+                .filter(|attr| !is_implied_namespace_declaration(name, attr))
+                .map(new_attribute)
+                .collect::<Vec<_>>();
+            let tag_name = name.local.to_string();
+            let has_children = !node.children.borrow().is_empty();
+            let elem = Element::new(
+                tag_name.clone(),
+                name.ns == ns!(html),
+                has_children,
+                attributes,
+            );
+            doc.diagnostics.extend(elem.diagnostics(config));
+
+            let new_parent = match tag_name.as_str() {
+                "head" => &Parent::Head,
+                "body" => &Parent::Body,
+                _ => parent,
+            };
+
+            let mut content = vec![];
+
+            if is_element(node, RAW_TEXT_ELEMENTS) {
+                let text = text_content(node);
+
+                if !text.is_empty() {
+                    content.push(pretty::text(format!(
+                        "(maud::PreEscaped({}))",
+                        raw_string_literal(&text)
+                    )));
+                }
+            } else {
+                for child in node.children.borrow().iter() {
+                    let child_content = walk(config, depth + 1, child, doc, new_parent)?;
+
+                    // Comments in the html element are added right away, so they stay in order
+                    // with the head and body content
+                    if new_parent == &Parent::Other {
+                        doc.extend(new_parent, child_content);
+                    } else {
+                        content.extend(child_content);
+                    }
+                }
+
+                // The parser puts template children in a separate document fragment
+                if let Some(contents) = template_contents {
+                    for child in contents.children.borrow().iter() {
+                        content.extend(walk(config, depth + 1, child, doc, new_parent)?);
+                    }
+                }
+            }
+
+            let is_inline = template_contents.is_none() && has_inline_content(config, node);
+
+            if parent == &Parent::Other {
+                doc.set_root_element(config, &elem);
+                doc.extend(new_parent, content);
+                Ok(vec![])
+            } else if elem.is_void(config) {
+                Ok(vec![elem.to_maud(config)])
+            } else if !elem.is_html && content.is_empty() {
+                // Maud has no self-closing syntax, so childless foreign elements are closed
+                // right away, i.e. path d="..." {}
+                Ok(vec![pretty::concat(vec![
+                    elem.to_maud(config),
+                    pretty::text("}"),
+                ])])
+            } else {
+                Ok(vec![element_block(
+                    config,
+                    elem.to_maud(config),
+                    content,
+                    is_inline,
+                )])
+            }
+        }
+
+        NodeData::ProcessingInstruction { target, .. } => Err(ConvertError::UnsupportedNode(
+            format!("processing instruction <?{}>", target),
+        )),
+    }
+}
+
+// Line comments must end the line, so only text and inline elements may share a line
+fn has_inline_content(config: &Config, node: &Handle) -> bool {
+    node.children
+        .borrow()
+        .iter()
+        .all(|child| match &child.data {
+            NodeData::Text { .. } => true,
+            NodeData::Comment { .. } => config.comment_style == CommentStyle::Omit,
+            NodeData::Element { .. } => !is_block(child) && !is_foreign(child),
+            _ => false,
+        })
+}
+
+// The html parser assigns the namespaces of svg and mathml elements, and knows the xlink
+// namespace, so declaring them has no effect
+fn is_implied_namespace_declaration(element: &QualName, attr: &html5ever::Attribute) -> bool {
+    let prefix = attr.name.prefix.as_deref().unwrap_or_default();
+
+    match (prefix, &*attr.name.local) {
+        ("", "xmlns") => *attr.value == *element.ns,
+        ("xmlns", "xlink") => *attr.value == *ns!(xlink),
+        _ => false,
+    }
+}
+
+fn normalize_text(config: &Config, node: &Handle, text: &str) -> Option<String> {
+    let is_preformatted = |ancestor: &Handle| {
+        is_context_element(config, ancestor, PREFORMATTED_ELEMENTS)
+            || is_foreign_element(ancestor, FOREIGN_PREFORMATTED_ELEMENTS)
+    };
+
+    if ancestors(node).any(|ancestor| is_preformatted(&ancestor)) {
+        // A newline right after the start tag is dropped when parsing, so it must be doubled for
+        // a leading newline to survive
+        let starts_element = adjacent_siblings(node).0.is_none()
+            && parent_node(node).is_some_and(|parent| {
+                is_context_element(config, &parent, LEADING_NEWLINE_ELEMENTS)
+            });
+
+        let text = match text.starts_with('\n') && starts_element {
+            true => format!("\n{}", text),
+            false => text.to_string(),
+        };
+
+        return Some(text).filter(|text| !text.is_empty());
+    }
+
+    let text = collapse_whitespace(text);
+
+    let (parent_is_block, parent_drops_whitespace) = match parent_node(node) {
+        Some(parent) if is_foreign(&parent) => {
+            (true, !is_foreign_element(&parent, FOREIGN_TEXT_ELEMENTS))
+        }
+
+        Some(parent) => (
+            is_context_element(config, &parent, BLOCK_ELEMENTS),
+            is_context_element(config, &parent, WHITESPACE_INSENSITIVE_ELEMENTS),
+        ),
+        None => (true, true),
+    };
+
+    let (prev, next) = adjacent_siblings(node);
+
+    // Text is only split by comments, which are skipped, so the whitespace around them collapses
+    let follows_whitespace = matches!(
+        prev.as_ref().map(|n| &n.data),
+        Some(NodeData::Text { contents }) if contents.borrow().ends_with(is_html_whitespace)
+    );
+
+    let starts_line = parent_drops_whitespace
+        || follows_whitespace
+        || prev.map(|n| is_block(&n)).unwrap_or(parent_is_block);
+    let ends_line =
+        parent_drops_whitespace || next.map(|n| is_block(&n)).unwrap_or(parent_is_block);
+
+    let text = if starts_line {
+        text.trim_start()
+    } else {
+        &text
+    };
+    let text = if ends_line { text.trim_end() } else { text };
+
+    Some(text.to_string()).filter(|text| !text.is_empty())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut prev_was_whitespace = false;
+
+    for c in text.chars() {
+        let is_whitespace = is_html_whitespace(c);
+
+        if !is_whitespace {
+            collapsed.push(c);
+        } else if !prev_was_whitespace {
+            collapsed.push(' ');
+        }
+
+        prev_was_whitespace = is_whitespace;
+    }
+
+    collapsed
+}
+
+// Only ascii whitespace collapses in html, i.e. non-breaking spaces are kept
+fn is_html_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+fn is_block(node: &Handle) -> bool {
+    matches!(node.data, NodeData::Document) || is_element(node, BLOCK_ELEMENTS)
+}
+
+// The tag name tables only apply to html elements, i.e. an svg title or style is not affected
+fn is_element(node: &Handle, tag_names: &[&str]) -> bool {
+    match &node.data {
+        NodeData::Element { name, .. } => name.ns == ns!(html) && tag_names.contains(&&*name.local),
+        _ => false,
+    }
+}
+
+// In fragment mode the top level nodes are children of a synthetic html root element, which
+// stands in for the context element
+fn is_context_element(config: &Config, node: &Handle, tag_names: &[&str]) -> bool {
+    let is_fragment_root =
+        parent_node(node).is_some_and(|parent| matches!(parent.data, NodeData::Document));
+
+    match &config.parse_mode {
+        ParseMode::Fragment(context) if is_fragment_root => tag_names.contains(&context.as_str()),
+
+        _ => is_element(node, tag_names),
+    }
+}
+
+// Svg and mathml elements, the names are case sensitive
+fn is_foreign_element(node: &Handle, tag_names: &[&str]) -> bool {
+    match &node.data {
+        NodeData::Element { name, .. } => name.ns != ns!(html) && tag_names.contains(&&*name.local),
+        _ => false,
+    }
+}
+
+fn is_foreign(node: &Handle) -> bool {
+    matches!(&node.data, NodeData::Element { name, .. } if name.ns != ns!(html))
+}
+
+fn parent_node(node: &Handle) -> Option<Handle> {
+    let weak_parent = node.parent.take();
+    let parent = weak_parent.as_ref().and_then(|weak| weak.upgrade());
+    node.parent.set(weak_parent);
+    parent
+}
+
+fn ancestors(node: &Handle) -> impl Iterator<Item = Handle> {
+    std::iter::successors(parent_node(node), parent_node)
+}
+
+// Returns the closest siblings that affect rendering, i.e. comments are skipped
+fn adjacent_siblings(node: &Handle) -> (Option<Handle>, Option<Handle>) {
+    let parent = match parent_node(node) {
+        Some(parent) => parent,
+        None => return (None, None),
+    };
+
+    let siblings = parent.children.borrow();
+    let is_rendered = |sibling: &&Handle| !matches!(sibling.data, NodeData::Comment { .. });
 
-                if render_only_body {
-                    self.render_body()
-                } else {
-                    self.render_full()
-                }
-            }
-            Render::Full => self.render_full(),
-            Render::OnlyBody => self.render_body(),
+    match siblings
+        .iter()
+        .position(|sibling| Rc::ptr_eq(sibling, node))
+    {
+        Some(index) => {
+            let prev = siblings[..index].iter().rev().find(is_rendered).cloned();
+            let next = siblings[index + 1..].iter().find(is_rendered).cloned();
+            (prev, next)
         }
-    }
 
-    #[rustfmt::skip]
-    fn render_full(&self) -> String {
-        let head_str = self.indent_vec(&self.head, 8).join("\n");
-        let body_str = self.indent_vec(&self.body, 8).join("\n");
+        None => (None, None),
+    }
+}
 
-        vec![
-            "html! {",
-            "    (maud::DOCTYPE)",
-            "    head {",
-            &head_str,
-            "    }",
-            "    body {",
-            &body_str,
-            "    }",
-            "}",
-        ].join("\n")
+fn format_doctype(config: &Config, name: &str, public_id: &str, system_id: &str) -> Doctype {
+    if name == "html" && public_id.is_empty() && system_id.is_empty() {
+        return Doctype::Html5;
     }
 
-    #[rustfmt::skip]
-    fn render_body(&self) -> String {
-        let body_str = self.indent_vec(&self.body, 4).join("\n");
+    let external_id = match (public_id, system_id) {
+        ("", "") => "".to_string(),
+        ("", system_id) => format!(r#" SYSTEM "{}""#, system_id),
+        (public_id, "") => format!(r#" PUBLIC "{}""#, public_id),
+        (public_id, system_id) => format!(r#" PUBLIC "{}" "{}""#, public_id, system_id),
+    };
+
+    let doctype = format!("<!DOCTYPE {}{}>", name, external_id);
+    Doctype::Other(format!(
+        "(maud::PreEscaped({}))",
+        string_literal(&doctype, config)
+    ))
+}
+
+fn text_content(node: &Handle) -> String {
+    node.children
+        .borrow()
+        .iter()
+        .filter_map(|child| match &child.data {
+            NodeData::Text { contents } => Some(contents.borrow().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn string_literal(value: &str, config: &Config) -> String {
+    let has_escapes = value.contains(is_quote_or_backslash);
+    let raw_allowed = value
+        .chars()
+        .all(|c| is_quote_or_backslash(c) || escape_char(c, &config.unicode_style).is_none());
 
-        vec![
-            "html! {",
-            &body_str,
-            "}",
-        ].join("\n")
+    match config.string_style {
+        StringStyle::Auto if has_escapes && raw_allowed => raw_string_literal(value),
+
+        _ => format!("\"{}\"", escape_string(value, config)),
     }
+}
 
-    fn push(&mut self, parent: &Parent, content: String) {
-        match parent {
-            Parent::Head => self.head.push(content),
-            Parent::Body => self.body.push(content),
-            Parent::Other => (),
+fn escape_string(value: &str, config: &Config) -> String {
+    value
+        .chars()
+        .map(|c| escape_char(c, &config.unicode_style).unwrap_or_else(|| c.to_string()))
+        .collect()
+}
+
+// Returns the escape sequence for characters that should not be written literally in a string
+fn escape_char(c: char, unicode_style: &UnicodeStyle) -> Option<String> {
+    match unicode_style {
+        UnicodeStyle::Escaped => {
+            let is_literal = c.is_ascii() && !c.is_ascii_control() && !is_quote_or_backslash(c);
+            Some(c.escape_default().to_string()).filter(|_| !is_literal)
         }
-    }
 
-    fn indent_vec(&self, v: &[String], indent: usize) -> Vec<String> {
-        v.iter()
-            .map(|s| format!("{:indent$}{}", "", s, indent = indent))
-            .collect()
+        UnicodeStyle::Readable => match c {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\0' => Some(c.escape_default().to_string()),
+            _ if c.is_control() || is_invisible(c) => Some(format!("\\u{{{:x}}}", c as u32)),
+            _ => None,
+        },
     }
 }
 
-#[derive(PartialEq, Eq)]
-enum Parent {
-    Head,
-    Body,
-    Other,
+fn is_quote_or_backslash(c: char) -> bool {
+    c == '"' || c == '\\'
 }
 
-pub fn html_to_maud(html: &str, config: &Config) -> String {
-    let mut input = html.as_bytes();
-    let dom = parse_document(RcDom::default(), Default::default())
-        .from_utf8()
-        .read_from(&mut input)
-        .unwrap(); // TODO: handle error
+// Whitespace other than a regular space and characters without a visible glyph are escaped to
+// make them stand out. Joiners and variation selectors are kept as they are part of emoji and
+// many scripts.
+fn is_invisible(c: char) -> bool {
+    (c.is_whitespace() && c != ' ')
+        || matches!(
+            c,
+            '\u{ad}'
+                | '\u{34f}'
+                | '\u{61c}'
+                | '\u{180e}'
+                | '\u{200b}'
+                | '\u{200e}'
+                | '\u{200f}'
+                | '\u{202a}'..='\u{202e}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{2066}'..='\u{2069}'
+                | '\u{feff}'
+        )
+}
 
-    let mut doc = Doc::new(html);
-    walk(config, 0, &dom.document, &mut doc, &Parent::Other);
+fn raw_string_literal(text: &str) -> String {
+    // The literal is terminated by a quote followed by as many hashes as it was opened with
+    let longest_hash_run = text
+        .split('"')
+        .skip(1)
+        .map(|after_quote| after_quote.chars().take_while(|c| *c == '#').count())
+        .max()
+        .unwrap_or(0);
 
-    doc.render(config)
+    let hashes = "#".repeat(longest_hash_run + 1);
+    format!("r{}\"{}\"{}", hashes, text, hashes)
 }
 
-fn walk(config: &Config, indent: usize, node: &Handle, doc: &mut Doc, parent: &Parent) {
-    match &node.data {
-        NodeData::Document => {
-            for child in node.children.borrow().iter() {
-                walk(config, indent + 4, child, doc, parent);
-            }
-        }
+fn format_comment(comment_style: &CommentStyle, contents: &str) -> Vec<String> {
+    let lines = comment_lines(contents);
 
-        NodeData::Doctype { .. } => {}
+    match comment_style {
+        CommentStyle::Omit => vec![],
 
-        NodeData::Text { contents } => {
-            let text = &contents.borrow();
-            let text = text.trim();
-            if !text.is_empty() {
-                let output = format!(
-                    "{:indent$}\"{}\"",
-                    "",
-                    text.escape_default(),
-                    indent = indent
-                );
+        CommentStyle::Line => lines
+            .iter()
+            .map(|line| format!("// {}", line).trim_end().to_string())
+            .collect(),
+
+        CommentStyle::Block => {
+            // Rust block comments nest, so both the opening and closing sequence must be broken up
+            let lines = lines
+                .iter()
+                .map(|line| line.replace("*/", "* /").replace("/*", "/ *"))
+                .collect::<Vec<_>>();
 
-                match parent {
-                    Parent::Head => doc.head.push(output),
-                    Parent::Body => doc.body.push(output),
-                    Parent::Other => (),
+            match lines.as_slice() {
+                [] => vec![],
+                [line] => vec![format!("/* {} */", line)],
+                _ => {
+                    let body = lines
+                        .iter()
+                        .map(|line| format!(" * {}", line).trim_end().to_string());
+
+                    std::iter::once("/*".to_string())
+                        .chain(body)
+                        .chain(std::iter::once(" */".to_string()))
+                        .collect()
                 }
             }
         }
+    }
+}
 
-        NodeData::Comment { .. } => {
-            //println!("{:indent$}/* {} */", "", contents, indent = indent)
-        }
+// Splits a comment into lines with the surrounding blank lines and common indentation removed
+fn comment_lines(contents: &str) -> Vec<String> {
+    let lines = contents
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .collect::<Vec<_>>();
 
-        NodeData::Element { name, attrs, .. } => {
-            let attributes = attrs.borrow().iter().map(new_attribute).collect::<Vec<_>>();
-            let tag_name = name.local.to_string();
-            let elem = Element::new(tag_name.clone(), attributes);
+    let last_non_blank = lines.iter().rposition(|line| !line.trim().is_empty());
+    let lines = &lines[..last_non_blank.map_or(0, |i| i + 1)];
 
-            let children = node.children.borrow();
+    let common_indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
 
-            let curly_or_semicolon = if is_empty_element(&tag_name) {
-                ";".to_string()
-            } else {
-                " {".to_string()
-            };
+    lines
+        .iter()
+        .map(|line| {
+            line.chars()
+                .skip(common_indent)
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
 
-            let output = format!(
-                "{:indent$}{}{}",
-                "",
-                elem.to_maud(config),
-                curly_or_semicolon,
-                indent = indent
-            );
-            doc.push(parent, output);
+// The contents of these elements are not html escaped, so maud must not escape them either.
+// Escapable raw text elements (title, textarea) are decoded by the parser and can be emitted as
+// regular strings. Noscript is raw text since the parser runs with scripting enabled.
+#[rustfmt::skip]
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "iframe", "noembed", "noframes", "noscript", "script", "style", "xmp",
+];
 
-            let new_parent = match tag_name.as_str() {
-                "head" => &Parent::Head,
-                "body" => &Parent::Body,
-                _ => parent,
-            };
+#[rustfmt::skip]
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while",
+    // Reserved for future use
+    "abstract", "become", "box", "do", "final", "gen", "macro", "override", "priv", "try",
+    "typeof", "unsized", "virtual", "yield",
+];
 
-            let new_indent = if tag_name == "head" || tag_name == "body" {
-                0
-            } else {
-                indent + 4
-            };
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "textarea", "listing", "plaintext"];
 
-            for child in children.iter() {
-                walk(config, new_indent, child, doc, new_parent);
-            }
+// The parser drops a newline directly after the start tag of these elements
+const LEADING_NEWLINE_ELEMENTS: &[&str] = &["pre", "textarea", "listing"];
 
-            if !is_empty_element(&tag_name) {
-                let output = format!("{:indent$}}}", "", indent = indent);
-                doc.push(parent, output);
-            }
-        }
+// Whitespace next to these elements ends up at the start or end of a line and is not rendered
+#[rustfmt::skip]
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "br", "caption", "col", "colgroup",
+    "dd", "details", "dialog", "div", "dl", "dt", "fieldset", "figcaption", "figure", "footer",
+    "form", "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hgroup", "hr", "html", "li",
+    "main", "menu", "nav", "ol", "optgroup", "option", "p", "pre", "section", "summary", "table",
+    "tbody", "td", "tfoot", "th", "thead", "title", "tr", "ul",
+];
 
-        NodeData::ProcessingInstruction { .. } => unreachable!(),
-    }
+// Text in foreign content is regular text, but scripts and styles are kept as written
+const FOREIGN_PREFORMATTED_ELEMENTS: &[&str] = &["script", "style"];
+
+// Whitespace in other foreign elements is not rendered
+#[rustfmt::skip]
+const FOREIGN_TEXT_ELEMENTS: &[&str] = &[
+    "desc", "text", "textPath", "title", "tspan", "annotation", "mi", "mn", "mo", "ms", "mtext",
+];
+
+// Whitespace directly inside these elements is never rendered
+#[rustfmt::skip]
+const WHITESPACE_INSENSITIVE_ELEMENTS: &[&str] = &[
+    "colgroup", "datalist", "frameset", "head", "html", "optgroup", "select", "table", "tbody",
+    "tfoot", "thead", "tr",
+];
+
+// The value of a boolean attribute must be empty or the attribute name, so only the name is
+// written. An empty value of any other attribute is kept, i.e. alt="" marks a decorative image.
+fn is_boolean_attribute(name: &str, value: &str) -> bool {
+    BOOLEAN_ATTRIBUTES.contains(&name) && (value.is_empty() || value.eq_ignore_ascii_case(name))
+}
+
+fn is_toggle_class(config: &Config, class: &str) -> bool {
+    config.toggle_style == ToggleStyle::Toggle && config.toggle_classes.iter().any(|c| c == class)
 }
 
 #[rustfmt::skip]
-fn is_empty_element(tag_name: &str) -> bool {
-    let void_tags = vec![
-        "area",
-        "base",
-        "br",
-        "col",
-        "embed",
-        "hr",
-        "img",
-        "input",
-        "link",
-        "meta",
-        "param",
-        "source",
-        "track",
-        "wbr",
-    ];
-
-    void_tags.into_iter().any(|s| tag_name == s)
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "allowfullscreen", "async", "autofocus", "autoplay", "checked", "compact", "controls",
+    "declare", "default", "defer", "disabled", "formnovalidate", "hidden", "inert", "ismap",
+    "itemscope", "loop", "multiple", "muted", "nohref", "nomodule", "noresize", "noshade",
+    "novalidate", "nowrap", "open", "playsinline", "readonly", "required", "reversed", "scoped",
+    "seamless", "selected", "typemustmatch",
+];
+
+// Html tag names are case insensitive, the parser lowercases them
+fn is_void_element(config: &Config, tag_name: &str) -> bool {
+    VOID_ELEMENTS.contains(&tag_name)
+        || config
+            .void_elements
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(tag_name))
 }
 
+// The void elements of the WHATWG spec, followed by obsolete ones that are still parsed as void
+#[rustfmt::skip]
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+    "basefont", "bgsound", "frame", "image", "keygen", "param",
+];
+
 #[derive(Debug)]
 struct Element {
     tag_name: String,
-    ids: Vec<String>,
+    // Svg and mathml elements are foreign
+    is_html: bool,
+    // A configured void element with children is written as a normal element, so that the
+    // children stay inside it
+    has_children: bool,
+    // The parser keeps one id, repeated ids are reported while parsing
+    id: Option<String>,
     classes: Vec<String>,
     attributes: Vec<(String, String)>,
+    // The kind of each attribute in source order
+    order: Vec<AttributeKind>,
+    duplicate_classes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttributeKind {
+    Id,
+    Classes,
+    Other,
 }
 
 impl Element {
-    pub fn new(tag_name: String, attrs: Vec<Attribute>) -> Element {
+    pub fn new(
+        tag_name: String,
+        is_html: bool,
+        has_children: bool,
+        attrs: Vec<Attribute>,
+    ) -> Element {
         let info = Element {
             tag_name,
-            ids: Vec::new(),
+            is_html,
+            has_children,
+            id: None,
             classes: Vec::new(),
             attributes: Vec::new(),
+            order: Vec::new(),
+            duplicate_classes: Vec::new(),
         };
 
         attrs.iter().fold(info, |mut info, attr| {
             match attr {
                 Attribute::Id(id) => {
-                    info.ids.push(id.to_string());
+                    info.id = Some(id.to_string());
+                    info.order.push(AttributeKind::Id);
                 }
 
+                // Classes are deduplicated keeping the first occurrence, which does not change
+                // how they apply
                 Attribute::Classes(classes) => {
-                    info.classes
-                        .extend(classes.split_whitespace().map(|s| s.to_string()));
+                    for class in classes.split_whitespace().map(|s| s.to_string()) {
+                        if !info.classes.contains(&class) {
+                            info.classes.push(class);
+                        } else if !info.duplicate_classes.contains(&class) {
+                            info.duplicate_classes.push(class);
+                        }
+                    }
+
+                    info.order.push(AttributeKind::Classes);
                 }
 
                 Attribute::Other { name, value } => {
                     info.attributes.push((name.to_string(), value.to_string()));
+                    info.order.push(AttributeKind::Other);
                 }
             }
 
@@ -271,17 +1715,89 @@ impl Element {
         })
     }
 
-    pub fn to_maud(&self, config: &Config) -> String {
-        vec![
-            self.format_tag_name(config),
-            self.format_id(&config.id_style),
-            self.format_classes(&config.class_style),
-            self.format_attributes(),
-        ]
-        .into_iter()
-        .filter(|s| !s.is_empty())
-        .collect::<Vec<String>>()
-        .join(" ")
+    pub fn diagnostics(&self, config: &Config) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+
+        if self.is_html && self.has_children && is_void_element(config, &self.tag_name) {
+            diagnostics.push(Diagnostic::VoidElementWithChildren {
+                tag_name: self.tag_name.clone(),
+            });
+        }
+
+        if !self.duplicate_classes.is_empty() {
+            diagnostics.push(Diagnostic::DuplicateClasses {
+                tag_name: self.tag_name.clone(),
+                classes: self.duplicate_classes.clone(),
+            });
+        }
+
+        if !config.maud_version.supports_quoted_attribute_names() {
+            diagnostics.extend(
+                self.attributes
+                    .iter()
+                    .filter(|(name, _)| !is_maud_attribute_name(name))
+                    .map(|(name, _)| Diagnostic::UnsupportedAttributeName {
+                        tag_name: self.tag_name.clone(),
+                        name: name.clone(),
+                        maud_version: config.maud_version.clone(),
+                    }),
+            );
+        }
+
+        diagnostics
+    }
+
+    // Returns the element up to the opening brace, or the semicolon of a void element. When it
+    // does not fit on one line the attributes are put on separate lines below the tag name.
+    pub fn to_maud(&self, config: &Config) -> pretty::Doc {
+        let tag_name = Some(self.format_tag_name(config))
+            .filter(|s| !s.is_empty())
+            .map(pretty::text);
+        let mut id = Some(self.format_id(config))
+            .filter(|s| !s.is_empty())
+            .map(pretty::text);
+        let mut classes = self.format_classes(config);
+        let mut attributes = self.format_attributes(config).into_iter();
+
+        let attributes = match config.attribute_order {
+            AttributeOrder::Grouped => id.into_iter().chain(classes).chain(attributes).collect(),
+
+            AttributeOrder::Source => self
+                .order
+                .iter()
+                .filter_map(|kind| match kind {
+                    AttributeKind::Id => id.take(),
+                    AttributeKind::Classes => classes.take(),
+                    AttributeKind::Other => attributes.next(),
+                })
+                .collect::<Vec<_>>(),
+        };
+
+        let mut parts = tag_name.into_iter().chain(attributes);
+
+        // The first attribute stays on the line of the tag name
+        let first = parts.by_ref().take(2).collect::<Vec<_>>();
+        let rest = parts.flat_map(|part| [pretty::line(), part]).collect();
+
+        let end = if self.is_void(config) {
+            pretty::text(";")
+        } else {
+            pretty::concat(vec![pretty::line(), pretty::text("{")])
+        };
+
+        pretty::group(pretty::concat(vec![
+            pretty::join(first, pretty::text(" ")),
+            pretty::nest(pretty::concat(rest)),
+            end,
+        ]))
+    }
+
+    fn is_void(&self, config: &Config) -> bool {
+        self.is_html && !self.has_children && is_void_element(config, &self.tag_name)
+    }
+
+    fn has_attributes(&self) -> bool {
+        self.id.is_some() || !self.classes.is_empty() || !self.attributes.is_empty()
     }
 
     fn format_tag_name(&self, config: &Config) -> String {
@@ -292,88 +1808,168 @@ impl Element {
         }
     }
 
-    fn format_id(&self, id_style: &IdStyle) -> String {
-        self.ids
-            .first()
-            .map(|id| self.format_id_helper(id_style, id))
+    fn format_id(&self, config: &Config) -> String {
+        self.id
+            .as_ref()
+            .map(|id| self.format_id_helper(config, id))
             .unwrap_or_else(|| "".to_string())
     }
 
-    fn format_id_helper(&self, id_style: &IdStyle, id: &str) -> String {
-        match id_style {
-            IdStyle::Full => format!(r#"id="{}""#, id),
-            IdStyle::Short | IdStyle::ShortNoDiv => format!("#{}", self.shorthand_quote(id)),
+    fn format_id_helper(&self, config: &Config, id: &str) -> String {
+        let use_shorthand = match config.id_style {
+            IdStyle::Full => false,
+            IdStyle::Short | IdStyle::ShortNoDiv => true,
+            IdStyle::Auto => shorthand_name(config, id) != ShorthandName::Escaped,
+        };
+
+        if use_shorthand {
+            format!("#{}", self.shorthand_quote(id, config))
+        } else {
+            format!("id={}", string_literal(id, config))
         }
     }
 
-    fn format_classes(&self, class_style: &ClassStyle) -> String {
-        match class_style {
-            ClassStyle::Full => {
-                let classes = self.classes.join(" ");
+    fn format_classes(&self, config: &Config) -> Option<pretty::Doc> {
+        if self.classes.is_empty() {
+            return None;
+        }
+
+        // Maud renders shorthand classes and a class attribute as two separate attributes, so all
+        // classes of an element use the same form. Toggles are only supported as shorthand.
+        let use_shorthand = match config.class_style {
+            _ if self
+                .classes
+                .iter()
+                .any(|class| is_toggle_class(config, class)) =>
+            {
+                true
+            }
+            ClassStyle::Full => false,
+            ClassStyle::Short | ClassStyle::ShortNoDiv => true,
+            ClassStyle::Auto => self
+                .classes
+                .iter()
+                .all(|class| shorthand_name(config, class) != ShorthandName::Escaped),
+        };
 
-                if classes.is_empty() {
-                    "".to_string()
+        match use_shorthand {
+            false => {
+                let literal = string_literal(&self.classes.join(" "), config);
+
+                // Long class lists are broken with string continuations, which skip the newline
+                // and the indentation of the next line
+                if literal.starts_with('"') {
+                    let classes = self
+                        .classes
+                        .iter()
+                        .map(|class| pretty::text(escape_string(class, config)))
+                        .collect();
+
+                    Some(pretty::concat(vec![
+                        pretty::text("class=\""),
+                        pretty::nest(pretty::fill(classes, pretty::line_break(" ", " \\"))),
+                        pretty::text("\""),
+                    ]))
                 } else {
-                    format!(r#"class="{}""#, classes)
+                    Some(pretty::text(format!("class={}", literal)))
                 }
             }
 
-            ClassStyle::Short | ClassStyle::ShortNoDiv => {
+            true => {
                 let classes = self
                     .classes
                     .iter()
-                    .map(|class| self.shorthand_quote(class))
-                    .collect::<Vec<_>>()
-                    .join(".");
+                    .map(|class| pretty::text(self.format_class_shorthand(config, class)))
+                    .collect();
 
-                if classes.is_empty() {
-                    "".to_string()
-                } else {
-                    format!(".{}", classes)
-                }
+                Some(pretty::nest(pretty::fill(classes, pretty::softline())))
             }
         }
     }
 
-    fn format_attributes(&self) -> String {
+    fn format_class_shorthand(&self, config: &Config, class: &str) -> String {
+        let class_name = self.shorthand_quote(class, config);
+
+        if is_toggle_class(config, class) {
+            format!(".{}[true]", class_name)
+        } else {
+            format!(".{}", class_name)
+        }
+    }
+
+    fn format_attributes(&self, config: &Config) -> Vec<pretty::Doc> {
         self.attributes
             .iter()
-            .map(|(name, value)| self.format_attribute(name, value))
-            .collect::<Vec<_>>()
-            .join(" ")
+            .map(|(name, value)| pretty::text(self.format_attribute(config, name, value)))
+            .collect()
     }
 
-    fn format_attribute(&self, name: &str, value: &str) -> String {
-        if value.is_empty() {
-            name.to_string()
+    fn format_attribute(&self, config: &Config, name: &str, value: &str) -> String {
+        let formatted_name = format_attribute_name(config, name);
+
+        if self.is_html && is_boolean_attribute(name, value) {
+            match config.toggle_style {
+                ToggleStyle::Static => formatted_name,
+                ToggleStyle::Toggle => format!("{}[true]", formatted_name),
+            }
         } else {
-            format!("{}=\"{}\"", name, value)
+            format!("{}={}", formatted_name, string_literal(value, config))
         }
     }
 
+    // The tag name can only be omitted when the element starts with an id or class shorthand
     fn should_omit_tag_name(&self, config: &Config) -> bool {
-        if self.tag_name != "div" {
-            false
-        } else {
-            let has_id = !self.ids.is_empty();
-            let has_class = !self.classes.is_empty();
-            let id_style_no_div = config.id_style == IdStyle::ShortNoDiv;
-            let class_style_no_div = config.class_style == ClassStyle::ShortNoDiv;
+        if !self.is_html || self.tag_name != "div" {
+            return false;
+        }
 
-            (has_id && id_style_no_div) || (has_class && class_style_no_div && !has_id)
+        let has_id = self.id.is_some();
+        let has_class = !self.classes.is_empty();
+
+        let first_attribute = match config.attribute_order {
+            AttributeOrder::Grouped if has_id => Some(AttributeKind::Id),
+            AttributeOrder::Grouped if has_class => Some(AttributeKind::Classes),
+            AttributeOrder::Grouped => None,
+            AttributeOrder::Source => self.order.first().copied(),
+        };
+
+        match first_attribute {
+            Some(AttributeKind::Id) => config.id_style == IdStyle::ShortNoDiv,
+            Some(AttributeKind::Classes) => {
+                has_class && config.class_style == ClassStyle::ShortNoDiv
+            }
+            _ => false,
         }
     }
 
-    fn shorthand_quote(&self, str: &str) -> String {
-        if self.shorthand_attr_required_quotes(str) {
-            format!("\"{}\"", str)
+    fn shorthand_quote(&self, name: &str, config: &Config) -> String {
+        if is_maud_name(name) {
+            name.to_string()
         } else {
-            str.to_string()
+            string_literal(name, config)
         }
     }
+}
+
+#[derive(PartialEq, Eq)]
+enum ShorthandName {
+    Bare,
+    Quoted,
+    // The name would need escapes in a string literal, which the auto styles leave to the full
+    // attribute to keep shorthand readable
+    Escaped,
+}
 
-    fn shorthand_attr_required_quotes(&self, str: &str) -> bool {
-        str.chars().any(char::is_numeric) || str.chars().any(|c| c == ':')
+fn shorthand_name(config: &Config, name: &str) -> ShorthandName {
+    if is_maud_name(name) {
+        ShorthandName::Bare
+    } else if name
+        .chars()
+        .all(|c| escape_char(c, &config.unicode_style).is_none())
+    {
+        ShorthandName::Quoted
+    } else {
+        ShorthandName::Escaped
     }
 }
 
@@ -384,14 +1980,436 @@ enum Attribute {
 }
 
 fn new_attribute(attr: &html5ever::Attribute) -> Attribute {
-    match &attr.name.local[..] {
-        "id" => Attribute::Id(attr.value.to_string()),
+    match (&attr.name.prefix, &attr.name.local[..]) {
+        (None, "id") => Attribute::Id(attr.value.to_string()),
 
-        "class" => Attribute::Classes(attr.value.to_string()),
+        (None, "class") => Attribute::Classes(attr.value.to_string()),
 
         _ => Attribute::Other {
-            name: attr.name.local.to_string(),
+            name: qualified_attribute_name(&attr.name),
             value: attr.value.to_string(),
         },
     }
 }
+
+// Attributes in foreign content are namespaced by the parser, i.e. xlink:href is parsed as
+// prefix "xlink" and local name "href"
+fn qualified_attribute_name(name: &QualName) -> String {
+    match &name.prefix {
+        Some(prefix) if !prefix.is_empty() => format!("{}:{}", prefix, name.local),
+        _ => name.local.to_string(),
+    }
+}
+
+fn format_attribute_name(config: &Config, name: &str) -> String {
+    if is_maud_attribute_name(name) {
+        name.to_string()
+    } else {
+        string_literal(name, config)
+    }
+}
+
+// Maud accepts names optionally namespaced with a single colon
+fn is_maud_attribute_name(name: &str) -> bool {
+    match name.split_once(':') {
+        Some((namespace, local)) => is_maud_name(namespace) && is_maud_name(local),
+        None => is_maud_name(name),
+    }
+}
+
+// Maud reads a name as an identifier followed by dashes and identifiers, i.e. w-1 is not a name
+// since 1 is a literal token. A trailing dash would join the name with a following identifier.
+fn is_maud_name(name: &str) -> bool {
+    let tokens = lex_name(name);
+
+    tokens.first() == Some(&NameToken::Ident)
+        && tokens.last() == Some(&NameToken::Ident)
+        && !tokens.contains(&NameToken::Other)
+}
+
+#[derive(PartialEq, Eq)]
+enum NameToken {
+    Ident,
+    Dash,
+    Other,
+}
+
+// Splits a name into tokens the way the rust lexer does, as far as maud's name parser is
+// concerned. Literals, other punctuation and non-ascii characters all lex as Other.
+fn lex_name(name: &str) -> Vec<NameToken> {
+    let is_word_char = |c: &char| c.is_ascii_alphanumeric() || *c == '_';
+    let mut chars = name.chars().peekable();
+    let mut tokens = vec![];
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '-' => NameToken::Dash,
+
+            // Identifiers and number literals both extend over the following word characters,
+            // i.e. 2xl is a number with a suffix
+            _ if is_word_char(&c) => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(is_word_char) {
+                    word.push(c);
+                }
+
+                if is_rust_identifier(&word) {
+                    NameToken::Ident
+                } else {
+                    NameToken::Other
+                }
+            }
+
+            _ => NameToken::Other,
+        };
+
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn is_rust_identifier(str: &str) -> bool {
+    let mut chars = str.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            str != "_" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_maud(html: &str, config: &Config) -> String {
+        convert(html, config).unwrap().maud
+    }
+
+    fn fragment(context: &str) -> Config {
+        Config {
+            parse_mode: ParseMode::Fragment(context.to_string()),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn maud_names() {
+        assert!(is_maud_name("title"));
+        assert!(is_maud_name("text-xl"));
+        assert!(is_maud_name("data-user_id"));
+        assert!(is_maud_name("a--b"));
+    }
+
+    #[test]
+    fn names_maud_can_not_parse() {
+        assert!(!is_maud_name("w-1/2"));
+        assert!(!is_maud_name("-mt-px"));
+        assert!(!is_maud_name("2xl"));
+        assert!(!is_maud_name("r#x"));
+        assert!(!is_maud_name("mt-"));
+        assert!(!is_maud_name("_"));
+        assert!(!is_maud_name("hover:underline"));
+        assert!(!is_maud_name(""));
+    }
+
+    #[test]
+    fn keeps_spaces_next_to_inline_elements() {
+        let expected = r#"html! {
+    "Hello "
+    b {
+        "world"
+    }
+    "!"
+}"#;
+
+        assert_eq!(to_maud("Hello <b>world</b>!", &Config::default()), expected);
+        assert_eq!(to_maud("Hello <b>world</b>!", &fragment("body")), expected);
+    }
+
+    #[test]
+    fn keeps_spaces_between_inline_elements() {
+        let maud = to_maud("<p><b>a</b> <i>b</i></p>", &Config::default());
+
+        assert_eq!(
+            maud,
+            r#"html! {
+    p {
+        b {
+            "a"
+        }
+        " "
+        i {
+            "b"
+        }
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn trims_spaces_next_to_line_breaks_and_blocks() {
+        let maud = to_maud("<p>a <br> b</p><div>a <p>b</p> c</div>", &Config::default());
+
+        assert_eq!(
+            maud,
+            r#"html! {
+    p {
+        "a"
+        br;
+        "b"
+    }
+    div {
+        "a"
+        p {
+            "b"
+        }
+        "c"
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn collapses_spaces_around_comments() {
+        let config = Config {
+            comment_style: CommentStyle::Line,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            to_maud("<p>Hello <!-- c --> <b>x</b></p>", &config),
+            r#"html! {
+    p {
+        "Hello "
+        // c
+        b {
+            "x"
+        }
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn doubles_the_leading_newline_of_pre() {
+        // The parser drops the first newline, so the text starts with a single one
+        assert_eq!(
+            to_maud("<pre>\n\nx</pre>", &Config::default()),
+            r#"html! {
+    pre {
+        "\n\nx"
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn keeps_textarea_text_verbatim() {
+        assert_eq!(
+            to_maud(
+                "<textarea>\n\ny</textarea><textarea>  y </textarea>",
+                &Config::default()
+            ),
+            r#"html! {
+    textarea {
+        "\n\ny"
+    }
+    textarea {
+        "  y "
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn uses_the_fragment_context_for_whitespace() {
+        assert_eq!(
+            to_maud("<b>  x  </b>", &fragment("pre")),
+            r#"html! {
+    b {
+        "  x  "
+    }
+}"#
+        );
+
+        assert_eq!(
+            to_maud("<li>a</li> <li>b</li>", &fragment("ul")),
+            r#"html! {
+    li {
+        "a"
+    }
+    li {
+        "b"
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn auto_renders_documents_in_full() {
+        let render = |html| convert(html, &Config::default()).unwrap().render;
+
+        assert_eq!(render("<HTML lang=en><p>x</p>"), Render::Full);
+        assert_eq!(render("<!DOCTYPE html>"), Render::Full);
+        assert_eq!(render("<body class=x>y"), Render::Full);
+        assert_eq!(render("<div>x</div>"), Render::OnlyBody);
+        assert_eq!(
+            render(r#"<script>document.write("<body>")</script><p>x</p>"#),
+            Render::OnlyBody
+        );
+    }
+
+    fn diagnostics(html: &str, config: &Config) -> Vec<String> {
+        let conversion = convert(html, config).unwrap();
+        conversion
+            .diagnostics
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    fn lexed_attributes(source: &str) -> Vec<(String, Option<String>)> {
+        lex_start_tag(source).attributes
+    }
+
+    fn attribute(name: &str, value: Option<&str>) -> (String, Option<String>) {
+        (name.to_string(), value.map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn lexes_quoted_and_unquoted_attributes() {
+        assert_eq!(
+            lexed_attributes(r#"<DIV Class="a b" class='c "d"' class=e title = "x>y">"#),
+            vec![
+                attribute("class", Some(r#""a b""#)),
+                attribute("class", Some(r#"'c "d"'"#)),
+                attribute("class", Some("e")),
+                attribute("title", Some(r#""x>y""#)),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_bare_attributes_and_self_closing_tags() {
+        assert_eq!(
+            lexed_attributes(r#"<input disabled disabled/>"#),
+            vec![attribute("disabled", None), attribute("disabled", None)]
+        );
+
+        // A slash in an unquoted value is part of the value
+        assert_eq!(
+            lexed_attributes(r#"<a href=/x/ href="/y" />"#),
+            vec![
+                attribute("href", Some("/x/")),
+                attribute("href", Some(r#""/y""#))
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_character_references_in_values() {
+        let attributes = vec![
+            attribute("title", Some(r#""a &amp; b""#)),
+            attribute("title", Some("&lt;x&gt;")),
+            attribute("disabled", None),
+        ];
+
+        assert_eq!(
+            decode_attribute_values(&attributes),
+            vec!["a & b", "<x>", ""]
+        );
+    }
+
+    #[test]
+    fn merges_repeated_class_attributes() {
+        let html = r#"é日本 <p id=x ID="y" class="a" class='b c' title=t title="u&amp;v">q</p>"#;
+
+        assert_eq!(
+            to_maud(html, &Config::default()),
+            r#"html! {
+    "é日本"
+    p id="x" class="a b c" title="t" {
+        "q"
+    }
+}"#
+        );
+
+        assert_eq!(
+            diagnostics(html, &Config::default()),
+            vec![
+                "<p> has a repeated title attribute, dropping 'u&v' and keeping the first value",
+                "<p> has a repeated class attribute, which browsers ignore, merging 'b c' into the first one",
+                "<p> has multiple ids, keeping 'x' and dropping 'y'",
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_attributes_in_raw_text_are_not_tags() {
+        let html = "<div class=a class=b>";
+
+        assert_eq!(
+            to_maud(html, &fragment("textarea")),
+            r#"html! {
+    "<div class=a class=b>"
+}"#
+        );
+        assert!(diagnostics(html, &fragment("textarea")).is_empty());
+
+        let html = r#"<script>var a = "<i class=a class=b>";</script><b class=c class=d>x</b>"#;
+        assert_eq!(
+            diagnostics(html, &fragment("body")),
+            vec!["<b> has a repeated class attribute, which browsers ignore, merging 'd' into the first one"]
+        );
+    }
+
+    #[test]
+    fn svg_title_is_not_raw_text() {
+        let html = "<svg><title><b class=a class=b>x</b></title></svg>";
+
+        assert_eq!(
+            to_maud(html, &Config::default()),
+            r#"html! {
+    svg {
+        title {
+            b class="a b" {
+                "x"
+            }
+        }
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn void_elements_with_children_keep_them() {
+        let config = Config {
+            void_elements: vec!["WIDGET".to_string()],
+            ..Config::default()
+        };
+        let html = "<div><widget>x</widget><widget></widget><command>c</command></div>";
+
+        assert_eq!(
+            to_maud(html, &config),
+            r#"html! {
+    div {
+        widget {
+            "x"
+        }
+        widget;
+        command {
+            "c"
+        }
+    }
+}"#
+        );
+
+        assert_eq!(
+            diagnostics(html, &config),
+            vec!["<widget> is a void element but has children, it is written as a normal element to keep them"]
+        );
+    }
+}
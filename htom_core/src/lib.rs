@@ -1,2 +1,3 @@
 pub mod home_page;
 pub mod html_to_maud;
+mod pretty;
@@ -0,0 +1,371 @@
+// A pretty printer in the style of Wadler's "A prettier printer". Documents are built from text,
+// line breaks and groups, and each group is printed flat on a single line when it fits in the
+// remaining width, otherwise its line breaks are printed as newlines.
+
+const TAB_WIDTH: usize = 4;
+
+#[derive(Clone, Debug)]
+pub enum Doc {
+    Text(String),
+    // Printed as `flat` when the enclosing group is flat, otherwise as `trailing` followed by a
+    // newline
+    Line {
+        flat: &'static str,
+        trailing: &'static str,
+    },
+    // Always printed as a newline, a group containing it never fits on one line
+    HardLine,
+    Concat(Vec<Doc>),
+    // Indents the lines following line breaks by one level
+    Nest(Box<Doc>),
+    Group(Box<Doc>),
+    // Items alternate with separators, and a separator only breaks when the next item does not fit
+    // on the current line
+    Fill(Vec<Doc>),
+}
+
+pub fn text(text: impl Into<String>) -> Doc {
+    Doc::Text(text.into())
+}
+
+// A space or a newline
+pub fn line() -> Doc {
+    line_break(" ", "")
+}
+
+// Nothing or a newline
+pub fn softline() -> Doc {
+    line_break("", "")
+}
+
+pub fn line_break(flat: &'static str, trailing: &'static str) -> Doc {
+    Doc::Line { flat, trailing }
+}
+
+pub fn hardline() -> Doc {
+    Doc::HardLine
+}
+
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+pub fn nest(doc: Doc) -> Doc {
+    Doc::Nest(Box::new(doc))
+}
+
+pub fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+pub fn fill(items: Vec<Doc>, separator: Doc) -> Doc {
+    Doc::Fill(intersperse(items, separator))
+}
+
+pub fn join(docs: Vec<Doc>, separator: Doc) -> Doc {
+    Doc::Concat(intersperse(docs, separator))
+}
+
+fn intersperse(docs: Vec<Doc>, separator: Doc) -> Vec<Doc> {
+    let mut joined = Vec::with_capacity(docs.len() * 2);
+
+    for (index, doc) in docs.into_iter().enumerate() {
+        if index > 0 {
+            joined.push(separator.clone());
+        }
+
+        joined.push(doc);
+    }
+
+    joined
+}
+
+// Prints the document with lines indented by `indent` per level, starting at `base_level`
+pub fn render(doc: &Doc, width: usize, indent: &str, base_level: usize) -> String {
+    let mut printer = Printer {
+        width,
+        indent,
+        indent_width: indent.chars().map(char_width).sum(),
+        output: String::new(),
+        column: 0,
+        pending_indent: Some(base_level),
+    };
+
+    printer.print(doc, base_level);
+    printer.output
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+#[derive(Clone, Copy)]
+enum Cmd<'a> {
+    Doc(&'a Doc),
+    Fill(&'a [Doc]),
+}
+
+type Item<'a> = (usize, Mode, Cmd<'a>);
+
+struct Printer<'a> {
+    width: usize,
+    indent: &'a str,
+    indent_width: usize,
+    output: String,
+    column: usize,
+    // Indentation is written lazily so that blank lines have no trailing whitespace
+    pending_indent: Option<usize>,
+}
+
+impl<'a> Printer<'a> {
+    fn print(&mut self, doc: &Doc, base_level: usize) {
+        let mut stack: Vec<Item> = vec![(base_level, Mode::Break, Cmd::Doc(doc))];
+
+        while let Some((level, mode, cmd)) = stack.pop() {
+            let doc = match cmd {
+                Cmd::Doc(doc) => doc,
+                Cmd::Fill(items) => {
+                    self.print_fill(&mut stack, level, mode, items);
+                    continue;
+                }
+            };
+
+            match doc {
+                Doc::Text(text) => self.text(text),
+
+                Doc::Line { flat, trailing } => match mode {
+                    Mode::Flat => self.text(flat),
+                    Mode::Break => {
+                        self.text(trailing);
+                        self.newline(level);
+                    }
+                },
+
+                Doc::HardLine => self.newline(level),
+
+                Doc::Concat(docs) => {
+                    stack.extend(docs.iter().rev().map(|doc| (level, mode, Cmd::Doc(doc))));
+                }
+
+                Doc::Nest(doc) => stack.push((level + 1, mode, Cmd::Doc(doc))),
+
+                Doc::Group(doc) => {
+                    let flat = (level, Mode::Flat, Cmd::Doc(doc));
+                    let mode = if mode == Mode::Flat || self.fits(vec![flat], &stack) {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+
+                    stack.push((level, mode, Cmd::Doc(doc)));
+                }
+
+                Doc::Fill(items) => stack.push((level, mode, Cmd::Fill(items))),
+            }
+        }
+    }
+
+    // Pushes the first item of the fill and the separator after it, followed by the remaining
+    // items so they are handled on a later iteration
+    fn print_fill<'d>(
+        &self,
+        stack: &mut Vec<Item<'d>>,
+        level: usize,
+        mode: Mode,
+        items: &'d [Doc],
+    ) {
+        let (item, rest) = match items.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+
+        let fits_mode = |docs: &[&'d Doc]| {
+            let flat = docs
+                .iter()
+                .rev()
+                .map(|doc| (level, Mode::Flat, Cmd::Doc(doc)));
+
+            if mode == Mode::Flat || self.fits(flat.collect(), &[]) {
+                Mode::Flat
+            } else {
+                Mode::Break
+            }
+        };
+
+        if let [separator, next, ..] = rest {
+            stack.push((level, mode, Cmd::Fill(&rest[1..])));
+            stack.push((
+                level,
+                fits_mode(&[item, separator, next]),
+                Cmd::Doc(separator),
+            ));
+        }
+
+        stack.push((level, fits_mode(&[item]), Cmd::Doc(item)));
+    }
+
+    // Checks if the items fit on the rest of the current line, followed by the remaining content
+    // up to the next line break
+    fn fits<'d>(&self, mut items: Vec<Item<'d>>, rest: &[Item<'d>]) -> bool {
+        let mut remaining = self.width as isize - self.current_column() as isize;
+        let mut rest = rest.iter().rev();
+
+        loop {
+            if remaining < 0 {
+                return false;
+            }
+
+            let (level, mode, cmd) = match items.pop() {
+                Some(item) => item,
+                None => match rest.next() {
+                    Some(item) => *item,
+                    None => return true,
+                },
+            };
+
+            let doc = match cmd {
+                Cmd::Doc(doc) => doc,
+                Cmd::Fill(docs) => {
+                    items.extend(docs.iter().rev().map(|doc| (level, mode, Cmd::Doc(doc))));
+                    continue;
+                }
+            };
+
+            match doc {
+                Doc::Text(text) => match text.split_once('\n') {
+                    Some((first_line, _)) => {
+                        return mode == Mode::Break && text_width(first_line) as isize <= remaining;
+                    }
+                    None => remaining -= text_width(text) as isize,
+                },
+
+                Doc::Line { flat, trailing } => match mode {
+                    Mode::Flat => remaining -= text_width(flat) as isize,
+                    Mode::Break => return text_width(trailing) as isize <= remaining,
+                },
+
+                Doc::HardLine => return mode == Mode::Break,
+
+                Doc::Concat(docs) | Doc::Fill(docs) => {
+                    items.extend(docs.iter().rev().map(|doc| (level, mode, Cmd::Doc(doc))));
+                }
+
+                Doc::Nest(doc) => items.push((level + 1, mode, Cmd::Doc(doc))),
+
+                Doc::Group(doc) => items.push((level, mode, Cmd::Doc(doc))),
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        if let Some(level) = self.pending_indent.take() {
+            self.output.push_str(&self.indent.repeat(level));
+            self.column = level * self.indent_width;
+        }
+
+        self.output.push_str(text);
+
+        self.column = match text.rsplit_once('\n') {
+            Some((_, last_line)) => text_width(last_line),
+            None => self.column + text_width(text),
+        };
+    }
+
+    fn newline(&mut self, level: usize) {
+        self.output.push('\n');
+        self.column = 0;
+        self.pending_indent = Some(level);
+    }
+
+    fn current_column(&self) -> usize {
+        match self.pending_indent {
+            Some(level) => level * self.indent_width,
+            None => self.column,
+        }
+    }
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+fn char_width(c: char) -> usize {
+    if c == '\t' {
+        TAB_WIDTH
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<Doc> {
+        words.iter().map(|word| text(*word)).collect()
+    }
+
+    #[test]
+    fn group_breaks_when_it_does_not_fit() {
+        let doc = group(concat(vec![
+            text("div"),
+            nest(concat(vec![line(), text("id=\"title\"")])),
+            line(),
+            text("{"),
+        ]));
+
+        assert_eq!(render(&doc, 16, "    ", 0), "div id=\"title\" {");
+        assert_eq!(render(&doc, 15, "    ", 0), "div\n    id=\"title\"\n{");
+    }
+
+    #[test]
+    fn fill_wraps_items_that_do_not_fit() {
+        let classes = fill(
+            words(&["text-xl", "font-bold", "mt-4", "px-2"]),
+            line_break(" ", " \\"),
+        );
+        let doc = concat(vec![text("\""), nest(classes), text("\"")]);
+
+        assert_eq!(
+            render(&doc, 20, "    ", 0),
+            "\"text-xl font-bold \\\n    mt-4 px-2\""
+        );
+        assert_eq!(
+            render(&doc, 100, "    ", 0),
+            "\"text-xl font-bold mt-4 px-2\""
+        );
+    }
+
+    #[test]
+    fn base_indent_with_tabs() {
+        let doc = concat(vec![
+            text("div {"),
+            nest(concat(vec![hardline(), text("p")])),
+            hardline(),
+            text("}"),
+        ]);
+
+        assert_eq!(render(&doc, 100, "\t", 1), "\tdiv {\n\t\tp\n\t}");
+    }
+
+    #[test]
+    fn tabs_count_towards_the_width() {
+        let doc = group(concat(vec![text("aaaa"), line(), text("bbbb")]));
+
+        assert_eq!(render(&doc, 13, "\t", 1), "\taaaa bbbb");
+        assert_eq!(render(&doc, 12, "\t", 1), "\taaaa\n\tbbbb");
+    }
+
+    #[test]
+    fn blank_lines_have_no_indentation() {
+        let doc = nest(concat(vec![text("a"), hardline(), hardline(), text("b")]));
+
+        assert_eq!(render(&doc, 100, "  ", 0), "a\n\n  b");
+    }
+}